@@ -0,0 +1,29 @@
+use {
+    blaze_haskell::{
+        game_state::{Battlesnake, GameState},
+        planner::{PlannerConfig, find_plan},
+    },
+    criterion::{Criterion, criterion_group, criterion_main},
+    std::hint::black_box,
+};
+
+fn plan_benchmark(c: &mut Criterion) {
+    let game_state = GameState {
+        height: 11,
+        width: 11,
+        player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+        enemies: vec![
+            Battlesnake::new(&[(7, 2), (7, 3), (8, 3)]),
+            Battlesnake::new(&[(2, 1), (2, 2), (2, 3), (2, 4)]),
+        ],
+        food: vec![],
+        hazards: vec![],
+    };
+    let config = PlannerConfig::default();
+    c.bench_function("find_plan two enemies", |b| {
+        b.iter(|| find_plan(black_box(&game_state), &config, &[]))
+    });
+}
+
+criterion_group!(benches, plan_benchmark);
+criterion_main!(benches);
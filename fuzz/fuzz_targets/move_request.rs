@@ -0,0 +1,28 @@
+#![no_main]
+
+//! Feeds arbitrary bytes through everything a `/move` request goes through
+//! before the search proper: parsing, conversion to a `GameState` and one
+//! ply of planning. Anything that parses must either be rejected with an
+//! error or planned without panicking.
+
+use libfuzzer_sys::fuzz_target;
+
+use blaze_haskell::{
+    api_types::MoveRequest,
+    game_state::GameState,
+    planner::{self, PlannerConfig},
+};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(request) = serde_json::from_slice::<MoveRequest>(data) else {
+        return;
+    };
+    let Ok(game_state) = GameState::from_board(&request.board, &request.you.id) else {
+        return;
+    };
+    let config = PlannerConfig {
+        search_depth: 1,
+        ..PlannerConfig::default()
+    };
+    planner::find_plan(&game_state, &config, &[]);
+});
@@ -3,7 +3,9 @@ use {
     serde_json::Value,
 };
 
-#[derive(Serialize, Deserialize)]
+use crate::game_state::Move;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Game {
     pub id: String,
     pub ruleset: Value,
@@ -12,13 +14,13 @@ pub struct Game {
     pub source: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Coordinates {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Board {
     pub height: i32,
     pub width: i32,
@@ -27,7 +29,7 @@ pub struct Board {
     pub snakes: Vec<Battlesnake>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Battlesnake {
     pub id: String,
     pub name: String,
@@ -35,8 +37,82 @@ pub struct Battlesnake {
     pub body: Vec<Coordinates>,
     pub latency: String,
     pub head: Coordinates,
-    pub length: u8,
+    pub length: u16,
     pub shout: String,
     pub squad: String,
     pub customizations: Value,
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StartRequest {
+    pub game: Game,
+    pub turn: i32,
+    pub board: Board,
+    pub you: Battlesnake,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MoveDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MoveRequest {
+    pub game: Game,
+    pub turn: u32,
+    pub board: Board,
+    pub you: Battlesnake,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveResponse {
+    #[serde(rename = "move")]
+    pub move_direction: MoveDirection,
+    pub shout: Option<String>,
+}
+
+impl From<Move> for MoveResponse {
+    fn from(value: Move) -> Self {
+        match value {
+            Move::Up => MoveResponse {
+                move_direction: MoveDirection::Up,
+                shout: None,
+            },
+            Move::Down => MoveResponse {
+                move_direction: MoveDirection::Down,
+                shout: None,
+            },
+            Move::Left => MoveResponse {
+                move_direction: MoveDirection::Left,
+                shout: None,
+            },
+            Move::Right => MoveResponse {
+                move_direction: MoveDirection::Right,
+                shout: None,
+            },
+        }
+    }
+}
+
+impl From<MoveDirection> for Move {
+    fn from(value: MoveDirection) -> Self {
+        match value {
+            MoveDirection::Up => Move::Up,
+            MoveDirection::Down => Move::Down,
+            MoveDirection::Left => Move::Left,
+            MoveDirection::Right => Move::Right,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EndRequest {
+    pub game: Game,
+    pub turn: i32,
+    pub board: Board,
+    pub you: Battlesnake,
+}
@@ -0,0 +1,560 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::{
+        net::IpAddr,
+        path::{Path, PathBuf},
+    },
+};
+
+use crate::{
+    error::{Error, Result},
+    planner::PlannerConfig,
+};
+
+/// Environment variables starting with this override settings, e.g.
+/// `BLAZE_HASKELL_SERVER_PORT=8080` sets `port` in the `[server]` table.
+static ENV_PREFIX: &str = "BLAZE_HASKELL_";
+/// The environment variable naming the configuration file, if any.
+pub static CONFIG_FILE_VAR: &str = "BLAZE_HASKELL_CONFIG";
+static MAX_SEARCH_DEPTH: usize = 8;
+static MAX_LATENCY_MARGIN_MS: u64 = 1000;
+/// Prefixes taken by the server itself, or by the snake at the root in metric
+/// labels.
+static RESERVED_PREFIXES: [&str; 5] = ["metrics", "default", "healthz", "readyz", "version"];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub snake: SnakeConfig,
+    pub planner: PlannerConfig,
+    pub timing: TimingConfig,
+    pub logging: LoggingConfig,
+    /// Further snakes served by the same process, alongside the one at the
+    /// root.
+    pub personalities: Vec<Personality>,
+}
+
+/// A snake served under a path prefix. Any `snake` or `planner` settings it
+/// leaves out are taken from the top-level tables.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Personality {
+    /// The path segment the snake is served under, e.g. "aggressive" for
+    /// `/aggressive/move`.
+    pub prefix: String,
+    #[serde(default)]
+    pub snake: SnakeConfig,
+    #[serde(default)]
+    pub planner: PlannerConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub address: IpAddr,
+    pub port: u16,
+    /// How long to let the games in progress and requests in flight finish
+    /// after SIGTERM or SIGINT before exiting anyway.
+    pub drain_timeout_ms: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            address: [0, 0, 0, 0].into(),
+            port: 8000,
+            drain_timeout_ms: 5000,
+        }
+    }
+}
+
+/// How the snake presents itself to the game server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnakeConfig {
+    pub author: String,
+    pub color: String,
+    pub head: String,
+    pub tail: String,
+    pub version: String,
+    /// Say what the planner is up to in each move's `shout`.
+    pub shout: bool,
+}
+
+impl Default for SnakeConfig {
+    fn default() -> Self {
+        Self {
+            author: "Matt Gordon".into(),
+            color: "#00ff00".into(),
+            head: "default".into(),
+            tail: "default".into(),
+            version: "pre-alpha".into(),
+            shout: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    /// How much of each move's time limit to leave for the response to reach
+    /// the game server.
+    pub latency_margin_ms: u64,
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self {
+            latency_margin_ms: 150,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Where to write a JSON lines log of every move's decision, one file per
+    /// game. Nothing is written if this is unset.
+    pub decision_log_dir: Option<PathBuf>,
+    /// Where to record every game played, from `/start` to `/end`, one file
+    /// per game. Nothing is recorded if this is unset.
+    pub recording_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// Builds the configuration from, in increasing order of precedence, the
+    /// defaults, the TOML file at `file`, `BLAZE_HASKELL_*` variables in
+    /// `env`, and `overrides` (typically from the command line).
+    pub fn load(
+        file: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+        overrides: toml::Table,
+    ) -> Result<Self> {
+        let mut table = match file {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path).map_err(|e| {
+                    Error::io(
+                        format!("Could not read configuration file {}", path.display()),
+                        e,
+                    )
+                })?;
+                contents.parse::<toml::Table>().map_err(|e| {
+                    Error::invalid_config(
+                        format!("Could not parse configuration file {}", path.display()),
+                        e,
+                    )
+                })?
+            }
+            None => toml::Table::new(),
+        };
+        merge(&mut table, env_overrides(env)?);
+        merge(&mut table, overrides);
+        inherit_personality_settings(&mut table);
+        let config: Config = table
+            .try_into()
+            .map_err(|e| Error::invalid_config("Invalid configuration", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        validate_snake(&self.snake, "snake")?;
+        validate_planner(&self.planner, "planner")?;
+        if self.timing.latency_margin_ms > MAX_LATENCY_MARGIN_MS {
+            return Err(Error::config(format!(
+                "Invalid configuration: timing.latency_margin_ms must be at most {}, not {}.",
+                MAX_LATENCY_MARGIN_MS, self.timing.latency_margin_ms
+            )));
+        }
+        for (i, personality) in self.personalities.iter().enumerate() {
+            let prefix = &personality.prefix;
+            if prefix.is_empty()
+                || !prefix
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(Error::config(format!(
+                    "Invalid configuration: personalities[{}].prefix must be a non-empty path segment of letters, digits, '-' and '_', not \"{}\".",
+                    i, prefix
+                )));
+            }
+            if RESERVED_PREFIXES.contains(&prefix.as_str()) {
+                return Err(Error::config(format!(
+                    "Invalid configuration: personalities[{}].prefix \"{}\" is reserved.",
+                    i, prefix
+                )));
+            }
+            if self.personalities[..i]
+                .iter()
+                .any(|other| &other.prefix == prefix)
+            {
+                return Err(Error::config(format!(
+                    "Invalid configuration: more than one personality has the prefix \"{}\".",
+                    prefix
+                )));
+            }
+            validate_snake(&personality.snake, &format!("personalities[{}].snake", i))?;
+            validate_planner(
+                &personality.planner,
+                &format!("personalities[{}].planner", i),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_snake(snake: &SnakeConfig, name: &str) -> Result<()> {
+    let color = snake.color.strip_prefix('#').unwrap_or_default();
+    if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::config(format!(
+            "Invalid configuration: {}.color must be a hex color like \"#00ff00\", not \"{}\".",
+            name, snake.color
+        )));
+    }
+    Ok(())
+}
+
+fn validate_planner(planner: &PlannerConfig, name: &str) -> Result<()> {
+    if !(1..=MAX_SEARCH_DEPTH).contains(&planner.search_depth) {
+        return Err(Error::config(format!(
+            "Invalid configuration: {}.search_depth must be between 1 and {}, not {}.",
+            name, MAX_SEARCH_DEPTH, planner.search_depth
+        )));
+    }
+    for (key, value) in planner.weights.named() {
+        if !value.is_finite() {
+            return Err(Error::config(format!(
+                "Invalid configuration: {}.weights.{} must be a finite number, not {}.",
+                name, key, value
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fills in each personality's `snake` and `planner` tables from the
+/// top-level ones.
+fn inherit_personality_settings(table: &mut toml::Table) {
+    let Some(toml::Value::Array(personalities)) = table.get("personalities") else {
+        return;
+    };
+    let personalities = personalities
+        .iter()
+        .cloned()
+        .map(|mut personality| {
+            if let toml::Value::Table(personality) = &mut personality {
+                for key in ["snake", "planner"] {
+                    let mut inherited = match table.get(key) {
+                        Some(toml::Value::Table(t)) => t.clone(),
+                        _ => toml::Table::new(),
+                    };
+                    if let Some(toml::Value::Table(own)) = personality.remove(key) {
+                        merge(&mut inherited, own);
+                    }
+                    personality.insert(key.into(), toml::Value::Table(inherited));
+                }
+            }
+            personality
+        })
+        .collect();
+    table.insert("personalities".into(), toml::Value::Array(personalities));
+}
+
+/// Turns `BLAZE_HASKELL_<TABLE>_<KEY>` variables into a table of settings.
+/// Nested settings join their keys the same way, e.g.
+/// `BLAZE_HASKELL_PLANNER_WEIGHTS_FOOD`. Values of settings that are strings
+/// by default, or unset by default like paths, are taken as they are. Others
+/// are read as TOML where possible, so that numbers and booleans work.
+fn env_overrides(env: impl IntoIterator<Item = (String, String)>) -> Result<toml::Table> {
+    let defaults = toml::Table::try_from(Config::default())
+        .map_err(|e| Error::parse("Could not write out the default configuration", e))?;
+    let mut overrides = toml::Table::new();
+    for (name, value) in env {
+        let Some(setting) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        if name == CONFIG_FILE_VAR {
+            continue;
+        }
+        let setting = setting.to_lowercase();
+        let (path, default) = match setting_path(&defaults, &setting) {
+            Some(found) => found,
+            // An unknown table, which loading the settings will refuse.
+            None => match setting.split_once('_') {
+                Some((table, key)) => (vec![table.into(), key.into()], None),
+                None => {
+                    return Err(Error::config(format!(
+                        "Invalid configuration: environment variable {} does not name a setting.",
+                        name
+                    )));
+                }
+            },
+        };
+        let value = match default {
+            None | Some(toml::Value::String(_)) => toml::Value::String(value),
+            Some(_) => format!("value = {}", value)
+                .parse::<toml::Table>()
+                .ok()
+                .and_then(|mut t| t.remove("value"))
+                .unwrap_or(toml::Value::String(value)),
+        };
+        let (key, tables) = path.split_last().unwrap();
+        let table = tables.iter().fold(&mut overrides, |table, name| {
+            table
+                .entry(name)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .unwrap()
+        });
+        table.insert(key.clone(), value);
+    }
+    Ok(overrides)
+}
+
+/// Finds the setting in `defaults` that `name` spells out with its keys
+/// joined by `_`, trying longer keys first, along with its default. Within a
+/// known table, a name that matches none of its keys is taken as a key of its
+/// own, so that settings unset by default can be given. Returns `None` if
+/// `name` is not within any table of `defaults`.
+fn setting_path<'a>(
+    defaults: &'a toml::Table,
+    name: &str,
+) -> Option<(Vec<String>, Option<&'a toml::Value>)> {
+    let mut keys: Vec<_> = defaults
+        .iter()
+        .filter(|(key, _)| name.starts_with(key.as_str()))
+        .collect();
+    keys.sort_by_key(|(key, _)| std::cmp::Reverse(key.len()));
+    keys.into_iter().find_map(|(key, value)| {
+        let rest = &name[key.len()..];
+        if rest.is_empty() {
+            return Some((vec![key.clone()], Some(value)));
+        }
+        let (toml::Value::Table(table), Some(rest)) = (value, rest.strip_prefix('_')) else {
+            return None;
+        };
+        let (mut path, default) =
+            setting_path(table, rest).unwrap_or_else(|| (vec![rest.into()], None));
+        path.insert(0, key.clone());
+        Some((path, default))
+    })
+}
+
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::Strategy;
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|&(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let config = Config::load(None, vec![], toml::Table::new()).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn layers_override_in_order() {
+        let path = std::env::temp_dir().join("blaze-haskell-layers-test.toml");
+        std::fs::write(
+            &path,
+            r##"
+[server]
+port = 9000
+
+[snake]
+color = "#123456"
+head = "smile"
+
+[planner]
+search_depth = 3
+strategy = "paranoid"
+"##,
+        )
+        .unwrap();
+        let overrides: toml::Table = "snake.head = \"bolt\"".parse().unwrap();
+        let config = Config::load(
+            Some(&path),
+            env(&[
+                ("BLAZE_HASKELL_SERVER_PORT", "9001"),
+                ("BLAZE_HASKELL_SNAKE_HEAD", "fang"),
+                ("BLAZE_HASKELL_PLANNER_AGGRESSIVE", "true"),
+                ("BLAZE_HASKELL_CONFIG", "ignored.toml"),
+                ("HOME", "/root"),
+            ]),
+            overrides,
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.server.port, 9001);
+        assert_eq!(config.snake.color, "#123456");
+        assert_eq!(config.snake.head, "bolt");
+        assert_eq!(config.snake.tail, "default");
+        assert_eq!(config.planner.search_depth, 3);
+        assert_eq!(config.planner.strategy, Strategy::Paranoid);
+        assert!(config.planner.aggressive);
+    }
+
+    #[test]
+    fn env_values_that_are_not_toml_are_strings() {
+        let config = Config::load(
+            None,
+            env(&[
+                ("BLAZE_HASKELL_SNAKE_COLOR", "#abcdef"),
+                ("BLAZE_HASKELL_SERVER_ADDRESS", "127.0.0.1"),
+                ("BLAZE_HASKELL_LOGGING_DECISION_LOG_DIR", "/var/log/snake"),
+            ]),
+            toml::Table::new(),
+        )
+        .unwrap();
+        assert_eq!(config.snake.color, "#abcdef");
+        assert_eq!(config.server.address, IpAddr::from([127, 0, 0, 1]));
+        assert_eq!(
+            config.logging.decision_log_dir,
+            Some(PathBuf::from("/var/log/snake"))
+        );
+    }
+
+    #[test]
+    fn env_values_of_string_settings_stay_strings() {
+        let config = Config::load(
+            None,
+            env(&[
+                ("BLAZE_HASKELL_SNAKE_VERSION", "1.0"),
+                ("BLAZE_HASKELL_SNAKE_AUTHOR", "2"),
+                ("BLAZE_HASKELL_SNAKE_HEAD", "true"),
+                ("BLAZE_HASKELL_SNAKE_SHOUT", "true"),
+                ("BLAZE_HASKELL_TIMING_LATENCY_MARGIN_MS", "200"),
+            ]),
+            toml::Table::new(),
+        )
+        .unwrap();
+        assert_eq!(config.snake.version, "1.0");
+        assert_eq!(config.snake.author, "2");
+        assert_eq!(config.snake.head, "true");
+        assert!(config.snake.shout);
+        assert_eq!(config.timing.latency_margin_ms, 200);
+    }
+
+    #[test]
+    fn env_values_reach_nested_and_multi_word_settings() {
+        let config = Config::load(
+            None,
+            env(&[
+                ("BLAZE_HASKELL_PLANNER_WEIGHTS_FOOD", "7.5"),
+                ("BLAZE_HASKELL_PLANNER_WEIGHTS_HEAD_TO_HEAD_PENALTY", "-3"),
+                ("BLAZE_HASKELL_PLANNER_SEARCH_DEPTH", "4"),
+                ("BLAZE_HASKELL_SERVER_DRAIN_TIMEOUT_MS", "100"),
+            ]),
+            toml::Table::new(),
+        )
+        .unwrap();
+        assert_eq!(config.planner.weights.food, 7.5);
+        assert_eq!(config.planner.weights.head_to_head_penalty, -3.0);
+        assert_eq!(config.planner.search_depth, 4);
+        assert_eq!(config.server.drain_timeout_ms, 100);
+        assert!(
+            Config::load(
+                None,
+                env(&[("BLAZE_HASKELL_PLANNER_WEIGHTS_NOPE", "1")]),
+                toml::Table::new()
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn personalities_inherit_top_level_settings() {
+        let overrides: toml::Table = r##"
+[snake]
+color = "#123456"
+head = "smile"
+
+[planner]
+search_depth = 3
+
+[[personalities]]
+prefix = "aggressive"
+snake = { color = "#ff0000" }
+planner = { aggressive = true }
+
+[[personalities]]
+prefix = "cautious"
+planner = { strategy = "paranoid", search_depth = 2 }
+"##
+        .parse()
+        .unwrap();
+        let config = Config::load(None, vec![], overrides).unwrap();
+        assert_eq!(config.personalities.len(), 2);
+        let aggressive = &config.personalities[0];
+        assert_eq!(aggressive.prefix, "aggressive");
+        assert_eq!(aggressive.snake.color, "#ff0000");
+        assert_eq!(aggressive.snake.head, "smile");
+        assert_eq!(aggressive.planner.search_depth, 3);
+        assert!(aggressive.planner.aggressive);
+        let cautious = &config.personalities[1];
+        assert_eq!(cautious.snake.color, "#123456");
+        assert_eq!(cautious.planner.search_depth, 2);
+        assert_eq!(cautious.planner.strategy, Strategy::Paranoid);
+        assert!(!cautious.planner.aggressive);
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        for overrides in [
+            "snake.color = \"green\"",
+            "planner.search_depth = 0",
+            "planner.strategy = \"optimistic\"",
+            "planner.weights.win = nan",
+            "planner.weights.lose = -inf",
+            "planner.weights.winning = 5.0",
+            "timing.latency_margin_ms = 5000",
+            "server.port = \"eighty\"",
+            "snake.colour = \"#00ff00\"",
+            "personalities = [{ prefix = \"a/b\" }]",
+            "personalities = [{ prefix = \"\" }]",
+            "personalities = [{ prefix = \"metrics\" }]",
+            "personalities = [{ prefix = \"a\" }, { prefix = \"a\" }]",
+            "personalities = [{ prefix = \"a\", planner = { search_depth = 99 } }]",
+            "personalities = [{ snake = { color = \"#00ff00\" } }]",
+        ] {
+            assert!(
+                Config::load(None, vec![], overrides.parse().unwrap()).is_err(),
+                "{}",
+                overrides
+            );
+        }
+        assert!(
+            Config::load(
+                None,
+                env(&[("BLAZE_HASKELL_PORT", "80")]),
+                toml::Table::new()
+            )
+            .is_err()
+        );
+        assert!(
+            Config::load(
+                Some(Path::new("/nonexistent/blaze-haskell.toml")),
+                vec![],
+                toml::Table::new()
+            )
+            .is_err()
+        );
+    }
+}
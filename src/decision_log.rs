@@ -0,0 +1,169 @@
+use {
+    serde::Serialize,
+    std::{
+        fs::OpenOptions,
+        io::Write,
+        path::{Path, PathBuf},
+        time::Duration,
+    },
+};
+
+use crate::{
+    api_types::Board,
+    error::{Error, Result},
+    game_state::Move,
+    planner::Plan,
+};
+
+/// Writes why the snake chose each move, so that lost games can be picked
+/// apart afterwards. Each game gets its own file of JSON lines, one per turn.
+#[derive(Debug, Clone)]
+pub struct DecisionLog {
+    dir: PathBuf,
+}
+
+/// One line of the decision log.
+#[derive(Debug, Serialize)]
+pub struct Decision {
+    pub turn: u32,
+    pub board: Board,
+    pub chosen: Move,
+    pub score: f32,
+    pub move_scores: Vec<MoveScore>,
+    pub search_depth: usize,
+    pub nodes_searched: usize,
+    pub elapsed_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MoveScore {
+    #[serde(rename = "move")]
+    pub player_move: Move,
+    pub score: f32,
+}
+
+impl Decision {
+    pub fn new(turn: u32, board: Board, plan: &Plan, elapsed: Duration) -> Self {
+        Self {
+            turn,
+            board,
+            chosen: plan.player_move,
+            score: plan.score,
+            move_scores: plan
+                .move_scores
+                .iter()
+                .map(|&(player_move, score)| MoveScore { player_move, score })
+                .collect(),
+            search_depth: plan.search_depth,
+            nodes_searched: plan.nodes_searched,
+            elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+impl DecisionLog {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The file holding the decisions `snake` made in `game_id`.
+    pub fn path(&self, game_id: &str, snake: &str) -> PathBuf {
+        self.dir.join(game_file_name(game_id, snake, "jsonl"))
+    }
+
+    /// Appends `decision` to the log for `game_id`, creating the directory
+    /// and file as needed.
+    pub fn record(&self, game_id: &str, snake: &str, decision: &Decision) -> Result<()> {
+        let path = self.path(game_id, snake);
+        let mut line = serde_json::to_string(decision)
+            .map_err(|e| Error::parse("Could not serialize decision", e))?;
+        line.push('\n');
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|()| OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| {
+                Error::io(
+                    format!("Could not write decision log {}", path.display()),
+                    e,
+                )
+            })
+    }
+}
+
+/// Names the file for what `snake` did in `game_id`. Characters that could
+/// escape the directory are replaced, and a hash of the raw id keeps games
+/// whose ids differ only in those characters apart.
+pub(crate) fn game_file_name(game_id: &str, snake: &str, extension: &str) -> String {
+    let sanitize = |s: &str| -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    format!(
+        "{}-{:08x}.{}.{}",
+        sanitize(game_id),
+        fnv1a(game_id.as_bytes()),
+        sanitize(snake),
+        extension
+    )
+}
+
+/// 32-bit FNV-1a, which unlike `std`'s hashers is the same on every build.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decisions_are_appended_as_json_lines() {
+        let dir = std::env::temp_dir().join("blaze-haskell-decision-log-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let log = DecisionLog::new(&dir);
+        let plan = Plan {
+            player_move: Move::Left,
+            score: 1.5,
+            move_scores: vec![(Move::Left, 1.5), (Move::Up, -20.0)],
+            search_depth: 2,
+            nodes_searched: 40,
+        };
+        let board = Board {
+            height: 11,
+            width: 11,
+            food: vec![],
+            hazards: vec![],
+            snakes: vec![],
+        };
+        for turn in 0..2 {
+            let decision = Decision::new(turn, board.clone(), &plan, Duration::from_millis(12));
+            log.record("../game/1", "default", &decision).unwrap();
+        }
+
+        let path = log.path("../game/1", "default");
+        assert_eq!(path, dir.join("___game_1-68e36878.default.jsonl"));
+        assert_ne!(log.path("game/1", "default"), log.path("game_1", "default"));
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["turn"], 1);
+        assert_eq!(lines[1]["chosen"], "left");
+        assert_eq!(lines[1]["move_scores"][1]["move"], "up");
+        assert_eq!(lines[1]["move_scores"][1]["score"], -20.0);
+        assert_eq!(lines[1]["search_depth"], 2);
+        assert_eq!(lines[1]["board"]["width"], 11);
+    }
+}
@@ -0,0 +1,145 @@
+use {
+    http_body_util::{BodyExt, Full},
+    hyper::{Request, body::Bytes, header},
+    hyper_util::{
+        client::legacy::{Client, connect::HttpConnector},
+        rt::TokioExecutor,
+    },
+    serde::Serialize,
+    std::{io, time::Duration},
+};
+
+use crate::error::{Error, Result};
+
+/// A client for the JSON APIs of Battlesnake servers, reusing connections
+/// between requests.
+#[derive(Clone, Debug)]
+pub(crate) struct JsonClient {
+    client: Client<HttpConnector, Full<Bytes>>,
+}
+
+impl JsonClient {
+    pub fn new() -> Self {
+        Self {
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        }
+    }
+
+    /// POSTs `body` as JSON to a plain `http://` URL and returns the body of
+    /// a successful response, giving up if the whole exchange takes longer
+    /// than `timeout`.
+    pub async fn post_json(
+        &self,
+        url: &str,
+        body: &impl Serialize,
+        timeout: Duration,
+    ) -> Result<Vec<u8>> {
+        let context = || format!("POST {} failed", url);
+        let body = serde_json::to_vec(body).map_err(|e| Error::parse(context(), e))?;
+        let request = Request::post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| Error::parse(context(), e))?;
+        let exchange = async {
+            let response = self
+                .client
+                .request(request)
+                .await
+                .map_err(|e| Error::io(context(), io::Error::other(e)))?;
+            let status = response.status();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| Error::io(context(), io::Error::other(e)))?
+                .to_bytes();
+            if !status.is_success() {
+                return Err(Error::invalid_text(format!(
+                    "{}: status {}",
+                    context(),
+                    status
+                )));
+            }
+            Ok(body.to_vec())
+        };
+        tokio::time::timeout(timeout, exchange).await.map_err(|_| {
+            let elapsed = io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no answer within {}ms", timeout.as_millis()),
+            );
+            Error::io(context(), elapsed)
+        })?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        axum::{Json, Router, routing::post},
+    };
+
+    /// Serves a route that echoes JSON on `address`, returning its URL, and a
+    /// `/slow` one next to it that takes five seconds to answer.
+    async fn echo_server(address: &str) -> std::io::Result<String> {
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+        let app = Router::new()
+            .route(
+                "/echo",
+                post(|Json(body): Json<serde_json::Value>| async { Json(body) }),
+            )
+            .route("/slow", post(|| tokio::time::sleep(Duration::from_secs(5))));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        Ok(format!("http://{}/echo", address))
+    }
+
+    #[test]
+    fn posts_json_to_ipv4_and_ipv6_hosts() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            let client = JsonClient::new();
+            let body = serde_json::json!({"move": "up"});
+            let timeout = Duration::from_secs(5);
+            let url = echo_server("127.0.0.1:0").await.unwrap();
+            let reply = client.post_json(&url, &body, timeout).await.unwrap();
+            assert_eq!(
+                serde_json::from_slice::<serde_json::Value>(&reply).unwrap(),
+                body
+            );
+
+            // Not every machine has IPv6 loopback.
+            if let Ok(url) = echo_server("[::1]:0").await {
+                assert!(url.starts_with("http://[::1]:"));
+                let reply = client.post_json(&url, &body, timeout).await.unwrap();
+                assert_eq!(
+                    serde_json::from_slice::<serde_json::Value>(&reply).unwrap(),
+                    body
+                );
+            }
+
+            let missing = url.replace("/echo", "/missing");
+            let error = client
+                .post_json(&missing, &body, timeout)
+                .await
+                .unwrap_err();
+            assert!(error.report().ends_with("status 404 Not Found"));
+            assert!(matches!(error, Error::Parse { .. }));
+
+            // Nothing listens on port 1.
+            let error = client
+                .post_json("http://127.0.0.1:1/echo", &body, timeout)
+                .await
+                .unwrap_err();
+            assert!(matches!(error, Error::Io { .. }));
+
+            let slow = url.replace("/echo", "/slow");
+            let error = client
+                .post_json(&slow, &body, Duration::from_millis(50))
+                .await
+                .unwrap_err();
+            assert!(error.report().ends_with("no answer within 50ms"));
+            assert!(matches!(error, Error::Io { .. }));
+        });
+    }
+}
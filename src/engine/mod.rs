@@ -0,0 +1,283 @@
+use {
+    futures_util::future::join_all,
+    std::time::{Duration, Instant},
+};
+
+use crate::{
+    api_types::{self, EndRequest, MoveRequest, StartRequest},
+    error::Result,
+    game_state::{Cell, Move},
+    planner::{PlannerConfig, SearchTree},
+};
+
+mod http;
+mod player;
+pub use player::{HttpPlayer, PlannerPlayer, Player};
+mod rules;
+pub use rules::{Arena, Elimination, EliminationCause, Snake, default_move};
+
+/// How local games are set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchSettings {
+    pub width: u16,
+    pub height: u16,
+    /// How long each snake has to answer each move.
+    pub timeout_ms: u32,
+    /// Games still going after this many turns are called a draw.
+    pub max_turns: Option<u32>,
+}
+
+impl Default for MatchSettings {
+    fn default() -> Self {
+        Self {
+            width: 11,
+            height: 11,
+            timeout_ms: 500,
+            max_turns: None,
+        }
+    }
+}
+
+/// How a local game ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameOutcome {
+    /// The index of the last snake standing, or `None` for a draw.
+    pub winner: Option<usize>,
+    pub turns: u32,
+    /// For each snake, how it went out, if it did.
+    pub eliminations: Vec<Option<Elimination>>,
+}
+
+/// Plays one game between `players` by the standard rules. Snakes that fail
+/// to answer in time carry on in the direction they were going, as on the
+/// official servers.
+pub async fn play_game(
+    players: &mut [Box<dyn Player>],
+    settings: &MatchSettings,
+    seed: u64,
+) -> Result<GameOutcome> {
+    let mut arena = Arena::new(settings.width, settings.height, players.len(), seed)?;
+    let game = api_types::Game {
+        id: format!("local-{}", seed),
+        ruleset: serde_json::json!({ "name": "standard" }),
+        map: "standard".into(),
+        timeout: settings.timeout_ms as i32,
+        source: "local".into(),
+    };
+    let names: Vec<String> = players.iter().map(|p| p.name().to_owned()).collect();
+    let mut latencies = vec![String::new(); players.len()];
+
+    let board = api_board(&arena, &names, &latencies);
+    join_all(players.iter_mut().enumerate().map(|(i, player)| {
+        player.start(StartRequest {
+            game: game.clone(),
+            turn: 0,
+            board: board.clone(),
+            you: api_snake(&arena, i, &names, &latencies),
+        })
+    }))
+    .await;
+
+    let timeout = Duration::from_millis(settings.timeout_ms.into());
+    while !arena.is_over() && settings.max_turns.is_none_or(|max| arena.turn < max) {
+        let board = api_board(&arena, &names, &latencies);
+        let answers = join_all(
+            players
+                .iter_mut()
+                .enumerate()
+                .filter(|(i, _)| arena.snakes[*i].is_alive())
+                .map(|(i, player)| {
+                    let request = MoveRequest {
+                        game: game.clone(),
+                        turn: arena.turn,
+                        board: board.clone(),
+                        you: api_snake(&arena, i, &names, &latencies),
+                    };
+                    async move {
+                        let start = Instant::now();
+                        let answer = tokio::time::timeout(timeout, player.choose_move(request))
+                            .await
+                            .ok()
+                            .flatten();
+                        (i, answer, start.elapsed())
+                    }
+                }),
+        )
+        .await;
+        let mut moves = vec![Move::Up; players.len()];
+        for (i, answer, latency) in answers {
+            moves[i] = answer.unwrap_or_else(|| default_move(&arena.snakes[i].body));
+            latencies[i] = latency.as_millis().to_string();
+        }
+        arena.step(&moves);
+    }
+
+    let board = api_board(&arena, &names, &latencies);
+    join_all(players.iter_mut().enumerate().map(|(i, player)| {
+        player.end(EndRequest {
+            game: game.clone(),
+            turn: arena.turn as i32,
+            board: board.clone(),
+            you: api_snake(&arena, i, &names, &latencies),
+        })
+    }))
+    .await;
+
+    Ok(GameOutcome::of(&arena))
+}
+
+/// Plays one game between our own planners, one snake per entry in
+/// `planners`, without going through the API. Each planner searches the board
+/// as a `GameState` to its full depth with no time limit, so the same seed
+/// always plays out the same game; `settings.timeout_ms` is not used.
+pub fn simulate_game(
+    planners: &[PlannerConfig],
+    settings: &MatchSettings,
+    seed: u64,
+) -> Result<GameOutcome> {
+    let mut arena = Arena::new(settings.width, settings.height, planners.len(), seed)?;
+    let mut trees: Vec<Option<SearchTree>> = planners.iter().map(|_| None).collect();
+    while !arena.is_over() && settings.max_turns.is_none_or(|max| arena.turn < max) {
+        let mut moves = vec![Move::Up; planners.len()];
+        for (i, planner) in planners.iter().enumerate() {
+            if !arena.snakes[i].is_alive() {
+                continue;
+            }
+            let game_state = arena.game_state(i);
+            let mut tree = trees[i]
+                .take()
+                .and_then(|tree| tree.advance(&game_state))
+                .unwrap_or_else(|| SearchTree::new(game_state));
+            moves[i] = tree.find_plan(planner, &[], None).player_move;
+            trees[i] = Some(tree);
+        }
+        arena.step(&moves);
+    }
+    Ok(GameOutcome::of(&arena))
+}
+
+impl GameOutcome {
+    fn of(arena: &Arena) -> Self {
+        Self {
+            winner: arena.winner(),
+            turns: arena.turn,
+            eliminations: arena.snakes.iter().map(|s| s.eliminated).collect(),
+        }
+    }
+}
+
+fn api_coordinates(&Cell(x, y): &Cell) -> api_types::Coordinates {
+    api_types::Coordinates {
+        x: x.into(),
+        y: y.into(),
+    }
+}
+
+/// The board as the game server would send it, leaving out eliminated
+/// snakes.
+fn api_board(arena: &Arena, names: &[String], latencies: &[String]) -> api_types::Board {
+    api_types::Board {
+        height: arena.height.into(),
+        width: arena.width.into(),
+        food: arena.food.iter().map(api_coordinates).collect(),
+        hazards: vec![],
+        snakes: (0..arena.snakes.len())
+            .filter(|&i| arena.snakes[i].is_alive())
+            .map(|i| api_snake(arena, i, names, latencies))
+            .collect(),
+    }
+}
+
+fn api_snake(
+    arena: &Arena,
+    i: usize,
+    names: &[String],
+    latencies: &[String],
+) -> api_types::Battlesnake {
+    let snake = &arena.snakes[i];
+    let body: Vec<_> = snake
+        .body
+        .head()
+        .iter()
+        .chain(snake.body.body())
+        .map(api_coordinates)
+        .collect();
+    api_types::Battlesnake {
+        id: format!("snake-{}", i),
+        name: names[i].clone(),
+        health: snake.health,
+        head: body[0].clone(),
+        length: body.len() as u16,
+        body,
+        latency: latencies[i].clone(),
+        shout: "".into(),
+        squad: "".into(),
+        customizations: serde_json::Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn planners() -> Vec<Box<dyn Player>> {
+        let planner = PlannerConfig {
+            search_depth: 1,
+            ..PlannerConfig::default()
+        };
+        (0..2)
+            .map(|i| {
+                Box::new(PlannerPlayer::new(&format!("planner-{}", i), planner, 0))
+                    as Box<dyn Player>
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn games_are_played_to_the_end() {
+        let settings = MatchSettings {
+            width: 7,
+            height: 7,
+            ..MatchSettings::default()
+        };
+        let outcome = play_game(&mut planners(), &settings, 3).await.unwrap();
+        assert!(outcome.turns > 0);
+        let survivors = outcome.eliminations.iter().filter(|e| e.is_none()).count();
+        assert!(survivors <= 1);
+        assert_eq!(outcome.winner.is_some(), survivors == 1);
+        assert_eq!(
+            play_game(&mut planners(), &settings, 3).await.unwrap(),
+            outcome
+        );
+    }
+
+    #[tokio::test]
+    async fn games_stop_at_the_turn_limit() {
+        let settings = MatchSettings {
+            max_turns: Some(5),
+            ..MatchSettings::default()
+        };
+        let outcome = play_game(&mut planners(), &settings, 1).await.unwrap();
+        assert_eq!(outcome.turns, 5);
+    }
+
+    #[test]
+    fn simulated_games_follow_the_seed() {
+        let planner = PlannerConfig {
+            search_depth: 1,
+            ..PlannerConfig::default()
+        };
+        let settings = MatchSettings {
+            width: 7,
+            height: 7,
+            ..MatchSettings::default()
+        };
+        let outcome = simulate_game(&[planner; 3], &settings, 11).unwrap();
+        assert!(outcome.turns > 0);
+        assert_eq!(outcome.eliminations.len(), 3);
+        assert_eq!(
+            simulate_game(&[planner; 3], &settings, 11).unwrap(),
+            outcome
+        );
+    }
+}
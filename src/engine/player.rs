@@ -0,0 +1,147 @@
+use {
+    futures_util::future::BoxFuture,
+    log::warn,
+    serde::Serialize,
+    std::time::{Duration, Instant},
+};
+
+use crate::{
+    api_types::{EndRequest, Game, MoveRequest, MoveResponse, StartRequest},
+    engine::http::JsonClient,
+    error::Result,
+    game_state::{GameState, Move},
+    planner::{self, PlannerConfig, SearchTree},
+    session::SessionRegistry,
+};
+
+/// A snake taking part in a local game.
+pub trait Player: Send {
+    fn name(&self) -> &str;
+
+    fn start(&mut self, request: StartRequest) -> BoxFuture<'_, ()>;
+
+    /// Returns `None` if the snake gave no usable answer.
+    fn choose_move(&mut self, request: MoveRequest) -> BoxFuture<'_, Option<Move>>;
+
+    fn end(&mut self, request: EndRequest) -> BoxFuture<'_, ()>;
+}
+
+/// Runs our own planner in-process, keeping a session across turns and
+/// falling back when the planner overruns just as the server does.
+pub struct PlannerPlayer {
+    name: String,
+    planner: PlannerConfig,
+    latency_margin_ms: u64,
+    sessions: SessionRegistry,
+}
+
+impl PlannerPlayer {
+    pub fn new(name: &str, planner: PlannerConfig, latency_margin_ms: u64) -> Self {
+        Self {
+            name: name.into(),
+            planner,
+            latency_margin_ms,
+            // Games end with `/end` rather than by going idle.
+            sessions: SessionRegistry::new(Duration::MAX),
+        }
+    }
+}
+
+impl Player for PlannerPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(&mut self, request: StartRequest) -> BoxFuture<'_, ()> {
+        self.sessions.start(&request.game.id);
+        Box::pin(async {})
+    }
+
+    fn choose_move(&mut self, request: MoveRequest) -> BoxFuture<'_, Option<Move>> {
+        Box::pin(async move {
+            let start = Instant::now();
+            let game_state = GameState::from_board(&request.board, &request.you.id)
+                .inspect_err(|e| warn!("{}", e.report()))
+                .ok()?;
+            let turn = planner::plan_turn(
+                &self.sessions,
+                &request,
+                game_state,
+                self.planner,
+                Duration::from_millis(self.latency_margin_ms),
+                start,
+                SearchTree::find_cancellable_plan,
+            )
+            .await;
+            Some(turn.player_move)
+        })
+    }
+
+    fn end(&mut self, request: EndRequest) -> BoxFuture<'_, ()> {
+        self.sessions.end(&request.game.id);
+        Box::pin(async {})
+    }
+}
+
+/// A snake served over HTTP, such as another copy of this server.
+pub struct HttpPlayer {
+    name: String,
+    url: String,
+    client: JsonClient,
+}
+
+impl HttpPlayer {
+    /// `url` is where the snake is served, e.g. `http://localhost:8000` or
+    /// `http://localhost:8000/aggressive`.
+    pub fn new(name: &str, url: &str) -> Self {
+        Self {
+            name: name.into(),
+            url: url.trim_end_matches('/').into(),
+            client: JsonClient::new(),
+        }
+    }
+
+    /// POSTs `request` to the snake's `path`, allowing it the game's timeout
+    /// to answer.
+    async fn post(&self, path: &str, game: &Game, request: &impl Serialize) -> Result<Vec<u8>> {
+        let timeout = Duration::from_millis(game.timeout.max(0) as u64);
+        let url = format!("{}/{}", self.url, path);
+        self.client.post_json(&url, request, timeout).await
+    }
+}
+
+impl Player for HttpPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn start(&mut self, request: StartRequest) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            if let Err(e) = self.post("start", &request.game, &request).await {
+                warn!("{}", e.report());
+            }
+        })
+    }
+
+    fn choose_move(&mut self, request: MoveRequest) -> BoxFuture<'_, Option<Move>> {
+        Box::pin(async move {
+            let body = self
+                .post("move", &request.game, &request)
+                .await
+                .inspect_err(|e| warn!("{}", e.report()))
+                .ok()?;
+            let response: MoveResponse = serde_json::from_slice(&body)
+                .inspect_err(|e| warn!("{} sent a bad move response: {}", self.name, e))
+                .ok()?;
+            Some(response.move_direction.into())
+        })
+    }
+
+    fn end(&mut self, request: EndRequest) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            if let Err(e) = self.post("end", &request.game, &request).await {
+                warn!("{}", e.report());
+            }
+        })
+    }
+}
@@ -0,0 +1,445 @@
+use {oorandom::Rand32, std::cmp::Reverse};
+
+use crate::{
+    error::{Error, Result},
+    game_state::{Battlesnake, Cell, Coord, GameState, MAX_BOARD_SIZE, Move},
+};
+
+static MAX_HEALTH: u8 = 100;
+static START_LENGTH: usize = 3;
+/// Food is spawned whenever there is less than this on the board.
+static MINIMUM_FOOD: usize = 1;
+/// The percent chance of one more food spawning on each turn.
+static FOOD_SPAWN_CHANCE: u32 = 15;
+
+/// Why a snake was eliminated. Collisions name the index of the other snake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EliminationCause {
+    OutOfHealth,
+    OutOfBounds,
+    SelfCollision,
+    Collision(usize),
+    HeadToHead(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elimination {
+    pub cause: EliminationCause,
+    /// The first turn the snake was missing from the board.
+    pub turn: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snake {
+    pub body: Battlesnake,
+    pub health: u8,
+    pub eliminated: Option<Elimination>,
+}
+
+impl Snake {
+    pub fn is_alive(&self) -> bool {
+        self.eliminated.is_none()
+    }
+}
+
+/// A game played by the official standard rules on the standard map.
+#[derive(Clone, Debug)]
+pub struct Arena {
+    pub width: u16,
+    pub height: u16,
+    pub turn: u32,
+    pub snakes: Vec<Snake>,
+    pub food: Vec<Cell>,
+    rng: Rand32,
+}
+
+impl Arena {
+    /// Sets up a game for `snake_count` snakes, placed as on the official
+    /// standard map. Everything random in the game follows from `seed`.
+    pub fn new(width: u16, height: u16, snake_count: usize, seed: u64) -> Result<Self> {
+        if width < 5 || height < 5 {
+            return Err(Error::config(format!(
+                "A {}x{} board is too small; it must be at least 5x5.",
+                width, height
+            )));
+        }
+        if i32::from(width.max(height)) > MAX_BOARD_SIZE {
+            return Err(Error::config(format!(
+                "A {}x{} board is too large; it must be at most {}x{}.",
+                width, height, MAX_BOARD_SIZE, MAX_BOARD_SIZE
+            )));
+        }
+        let mut rng = Rand32::new(seed);
+        let (mn, mx_x, mx_y) = (1, width as Coord - 2, height as Coord - 2);
+        let (md_x, md_y) = ((width as Coord - 1) / 2, (height as Coord - 1) / 2);
+        let mut corners = [
+            Cell(mn, mn),
+            Cell(mn, mx_y),
+            Cell(mx_x, mn),
+            Cell(mx_x, mx_y),
+        ];
+        let mut cardinals = [
+            Cell(mn, md_y),
+            Cell(md_x, mn),
+            Cell(md_x, mx_y),
+            Cell(mx_x, md_y),
+        ];
+        shuffle(&mut corners, &mut rng);
+        shuffle(&mut cardinals, &mut rng);
+        let mut starts = [corners, cardinals];
+        if rng.rand_range(0..2) == 0 {
+            starts.reverse();
+        }
+        let starts = starts.as_flattened();
+        if snake_count > starts.len() {
+            return Err(Error::config(format!(
+                "At most {} snakes can play, not {}.",
+                starts.len(),
+                snake_count
+            )));
+        }
+        let snakes: Vec<_> = starts[..snake_count]
+            .iter()
+            .map(|&Cell(x, y)| Snake {
+                body: Battlesnake::new(&[(x as usize, y as usize); START_LENGTH]),
+                health: MAX_HEALTH,
+                eliminated: None,
+            })
+            .collect();
+
+        // Each snake starts with food on a diagonal leading away from the
+        // centre, and there is food in the centre.
+        let centre = Cell(md_x, md_y);
+        let mut food = vec![];
+        for snake in &snakes {
+            let Cell(x, y) = snake.body.head().unwrap();
+            let options: Vec<_> = [
+                Cell(x - 1, y - 1),
+                Cell(x - 1, y + 1),
+                Cell(x + 1, y - 1),
+                Cell(x + 1, y + 1),
+            ]
+            .into_iter()
+            .filter(|&cell| {
+                let Cell(fx, fy) = cell;
+                let away_from_centre = (fx < x && x < centre.0)
+                    || (centre.0 < x && x < fx)
+                    || (fy < y && y < centre.1)
+                    || (centre.1 < y && y < fy);
+                let corner = (fx == 0 || fx == mx_x + 1) && (fy == 0 || fy == mx_y + 1);
+                cell != centre && !food.contains(&cell) && away_from_centre && !corner
+            })
+            .collect();
+            if options.is_empty() {
+                return Err(Error::new("Could not place starting food."));
+            }
+            food.push(options[rng.rand_range(0..options.len() as u32) as usize]);
+        }
+        food.push(centre);
+
+        Ok(Self {
+            width,
+            height,
+            turn: 0,
+            snakes,
+            food,
+            rng,
+        })
+    }
+
+    /// Plays one turn, in which each living snake makes its move in `moves`.
+    pub fn step(&mut self, moves: &[Move]) {
+        for (snake, &snake_move) in self.snakes.iter_mut().zip(moves) {
+            if snake.is_alive() {
+                snake.body = snake.body.update(snake_move, &self.food);
+                let head = snake.body.head().unwrap();
+                snake.health = if self.food.contains(&head) {
+                    MAX_HEALTH
+                } else {
+                    snake.health.saturating_sub(1)
+                };
+            }
+        }
+        let heads: Vec<_> = self.living().filter_map(|s| s.body.head()).collect();
+        self.food.retain(|food| !heads.contains(food));
+        self.eliminate();
+        self.turn += 1;
+        self.spawn_food();
+    }
+
+    pub fn is_over(&self) -> bool {
+        let remaining = self.living().count();
+        if self.snakes.len() == 1 {
+            remaining == 0
+        } else {
+            remaining <= 1
+        }
+    }
+
+    /// The last snake standing, once the game is over.
+    pub fn winner(&self) -> Option<usize> {
+        match self.snakes.len() {
+            1 => None,
+            _ if self.is_over() => self.snakes.iter().position(Snake::is_alive),
+            _ => None,
+        }
+    }
+
+    /// The board as snake `i` sees it, without the eliminated snakes, as
+    /// `GameState::from_board` would build it from the game server's request.
+    pub fn game_state(&self, i: usize) -> GameState {
+        GameState {
+            height: self.height,
+            width: self.width,
+            player: self.snakes[i].body.clone(),
+            enemies: (0..self.snakes.len())
+                .filter(|&j| j != i && self.snakes[j].is_alive())
+                .map(|j| self.snakes[j].body.clone())
+                .collect(),
+            food: self.food.clone(),
+            hazards: vec![],
+        }
+    }
+
+    pub fn living(&self) -> impl Iterator<Item = &Snake> {
+        self.snakes.iter().filter(|snake| snake.is_alive())
+    }
+
+    fn eliminate(&mut self) {
+        let turn = self.turn + 1;
+        let (width, height) = (self.width as i32, self.height as i32);
+        for snake in self.snakes.iter_mut().filter(|s| s.is_alive()) {
+            let cause = if snake.health == 0 {
+                EliminationCause::OutOfHealth
+            } else if snake.body.has_gone_oob(width, height) {
+                EliminationCause::OutOfBounds
+            } else {
+                continue;
+            };
+            snake.eliminated = Some(Elimination { cause, turn });
+        }
+
+        // Collisions are all judged before any of them is applied, so that
+        // both snakes in a collision can be eliminated.
+        let mut by_length: Vec<usize> = (0..self.snakes.len())
+            .filter(|&i| self.snakes[i].is_alive())
+            .collect();
+        by_length.sort_by_key(|&i| Reverse(self.snakes[i].body.length()));
+        let mut collisions = vec![];
+        for &i in &by_length {
+            let snake = &self.snakes[i].body;
+            let head = snake.head().unwrap();
+            let others = by_length.iter().copied().filter(|&j| j != i);
+            let cause = if snake.body().contains(&head) {
+                EliminationCause::SelfCollision
+            } else if let Some(j) = others
+                .clone()
+                .find(|&j| self.snakes[j].body.body().contains(&head))
+            {
+                EliminationCause::Collision(j)
+            } else if let Some(j) = others.clone().find(|&j| {
+                let other = &self.snakes[j].body;
+                other.head() == Some(head) && snake.length() <= other.length()
+            }) {
+                EliminationCause::HeadToHead(j)
+            } else {
+                continue;
+            };
+            collisions.push((i, cause));
+        }
+        for (i, cause) in collisions {
+            self.snakes[i].eliminated = Some(Elimination { cause, turn });
+        }
+    }
+
+    fn spawn_food(&mut self) {
+        if self.food.len() < MINIMUM_FOOD {
+            self.place_food_randomly(MINIMUM_FOOD - self.food.len());
+        } else if 100 - self.rng.rand_range(0..100) < FOOD_SPAWN_CHANCE {
+            self.place_food_randomly(1);
+        }
+    }
+
+    fn place_food_randomly(&mut self, count: usize) {
+        let mut free: Vec<Cell> = (0..self.height as Coord)
+            .flat_map(|y| (0..self.width as Coord).map(move |x| Cell(x, y)))
+            .filter(|cell| {
+                !self.food.contains(cell)
+                    && !self.living().any(|snake| {
+                        snake.body.head() == Some(*cell) || snake.body.body().contains(cell)
+                    })
+            })
+            .collect();
+        for _ in 0..count {
+            if free.is_empty() {
+                return;
+            }
+            let i = self.rng.rand_range(0..free.len() as u32) as usize;
+            self.food.push(free.swap_remove(i));
+        }
+    }
+}
+
+/// The move the game server makes for a snake that fails to answer in time:
+/// onwards in the direction it last moved, or up if it hasn't moved yet.
+pub fn default_move(snake: &Battlesnake) -> Move {
+    match (snake.head(), snake.body().first()) {
+        (Some(head), Some(&neck)) => Move::enumerate()
+            .find(|&m| neck + m == head)
+            .unwrap_or(Move::Up),
+        _ => Move::Up,
+    }
+}
+
+fn shuffle<T>(items: &mut [T], rng: &mut Rand32) {
+    for i in (1..items.len()).rev() {
+        let j = rng.rand_range(0..i as u32 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snake(cells: &[(usize, usize)]) -> Snake {
+        Snake {
+            body: Battlesnake::new(cells),
+            health: MAX_HEALTH,
+            eliminated: None,
+        }
+    }
+
+    fn arena(snakes: Vec<Snake>, food: Vec<Cell>) -> Arena {
+        Arena {
+            width: 11,
+            height: 11,
+            turn: 0,
+            snakes,
+            food,
+            rng: Rand32::new(0),
+        }
+    }
+
+    #[test]
+    fn setup_follows_the_seed() {
+        let a = Arena::new(11, 11, 4, 7).unwrap();
+        let b = Arena::new(11, 11, 4, 7).unwrap();
+        assert_eq!(a.snakes, b.snakes);
+        assert_eq!(a.food, b.food);
+        assert_eq!(a.food.len(), 5);
+        assert_eq!(a.food.last(), Some(&Cell(5, 5)));
+        for snake in &a.snakes {
+            let Cell(x, y) = snake.body.head().unwrap();
+            assert!([1, 5, 9].contains(&x) && [1, 5, 9].contains(&y));
+            assert_eq!(snake.body.length(), START_LENGTH);
+        }
+        assert!(Arena::new(11, 11, 9, 7).is_err());
+        assert!(Arena::new(3, 11, 2, 7).is_err());
+        assert!(Arena::new(11, 251, 2, 7).is_err());
+        assert!(Arena::new(250, 250, 2, 7).is_ok());
+    }
+
+    #[test]
+    fn snakes_grow_and_heal_by_eating() {
+        let mut target = arena(
+            vec![snake(&[(1, 1), (1, 2), (1, 3)]), snake(&[(8, 8), (8, 9)])],
+            vec![Cell(1, 0), Cell(5, 5)],
+        );
+        target.snakes[0].health = 40;
+        target.step(&[Move::Down, Move::Down]);
+        assert_eq!(target.snakes[0].health, MAX_HEALTH);
+        assert_eq!(target.snakes[0].body.length(), 4);
+        assert_eq!(target.snakes[1].health, MAX_HEALTH - 1);
+        assert!(!target.food.contains(&Cell(1, 0)));
+        assert_eq!(target.turn, 1);
+    }
+
+    #[test]
+    fn snakes_starve() {
+        let mut target = arena(
+            vec![snake(&[(1, 1), (1, 2)]), snake(&[(8, 8), (8, 9)])],
+            vec![Cell(5, 5)],
+        );
+        target.snakes[0].health = 1;
+        target.step(&[Move::Right, Move::Down]);
+        assert_eq!(
+            target.snakes[0].eliminated,
+            Some(Elimination {
+                cause: EliminationCause::OutOfHealth,
+                turn: 1
+            })
+        );
+        assert!(target.is_over());
+        assert_eq!(target.winner(), Some(1));
+    }
+
+    #[test]
+    fn collisions_eliminate_snakes() {
+        for (snakes, moves, expected) in [
+            // Equal lengths meeting head-on both die.
+            (
+                [&[(4, 1), (3, 1), (2, 1)], &[(6, 1), (7, 1), (8, 1)]],
+                [Move::Right, Move::Left],
+                [
+                    Some(EliminationCause::HeadToHead(1)),
+                    Some(EliminationCause::HeadToHead(0)),
+                ],
+            ),
+            // Only the shorter snake dies head-on.
+            (
+                [&[(4, 1), (3, 1), (2, 1)], &[(6, 1), (7, 1)]],
+                [Move::Right, Move::Left],
+                [None, Some(EliminationCause::HeadToHead(0))],
+            ),
+            // Running into another snake's body.
+            (
+                [&[(4, 1), (3, 1), (2, 1)], &[(3, 2), (3, 3), (3, 4)]],
+                [Move::Up, Move::Down],
+                [None, Some(EliminationCause::Collision(0))],
+            ),
+            // Running into its own body.
+            (
+                [&[(4, 1), (4, 2), (3, 2), (3, 1), (2, 1)], &[(8, 8), (8, 9)]],
+                [Move::Left, Move::Down],
+                [Some(EliminationCause::SelfCollision), None],
+            ),
+            // Off the board.
+            (
+                [&[(4, 0), (4, 1), (4, 2)], &[(8, 8), (8, 9)]],
+                [Move::Down, Move::Down],
+                [Some(EliminationCause::OutOfBounds), None],
+            ),
+        ] as [([&[(usize, usize)]; 2], _, _); 5]
+        {
+            let mut target = arena(snakes.map(snake).to_vec(), vec![Cell(5, 5)]);
+            target.step(&moves);
+            let causes: Vec<_> = target
+                .snakes
+                .iter()
+                .map(|s| s.eliminated.map(|e| e.cause))
+                .collect();
+            assert_eq!(causes, expected, "{:?}", snakes);
+        }
+    }
+
+    #[test]
+    fn food_spawns_below_the_minimum() {
+        let mut target = arena(
+            vec![snake(&[(1, 1), (1, 2)]), snake(&[(8, 8), (8, 9)])],
+            vec![Cell(1, 0)],
+        );
+        target.step(&[Move::Down, Move::Down]);
+        assert!(!target.food.is_empty());
+        assert!(!target.food.contains(&Cell(1, 0)));
+    }
+
+    #[test]
+    fn unanswered_snakes_keep_going() {
+        assert_eq!(
+            default_move(&Battlesnake::new(&[(2, 1), (1, 1), (0, 1)])),
+            Move::Right
+        );
+        assert_eq!(default_move(&Battlesnake::new(&[(2, 1); 3])), Move::Up);
+    }
+}
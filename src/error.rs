@@ -1,18 +1,237 @@
+use {
+    axum::http::StatusCode,
+    log::{error, warn},
+    std::time::Duration,
+};
+
+use crate::validation::ValidationError;
+
 pub type Result<T> = std::result::Result<T, Error>;
 
+type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
 #[derive(Debug)]
-pub struct Error {
-    msg: String,
+pub enum Error {
+    /// A request from the game server was malformed.
+    Validation(ValidationError),
+    /// The board has no snake with the id of the one to plan for.
+    MissingPlayer { snake: String },
+    /// The planner took longer than the game allows for a move.
+    PlannerTimeout {
+        elapsed: Duration,
+        timeout: Duration,
+    },
+    /// The configuration is unreadable or inconsistent.
+    Config {
+        context: String,
+        source: Option<BoxedError>,
+    },
+    /// Reading or writing a file or socket failed; `context` says which.
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+    /// Some text, such as a board, request or recording, could not be
+    /// understood, or a value could not be written out as text.
+    Parse {
+        context: String,
+        source: Option<BoxedError>,
+    },
+    Internal {
+        context: String,
+        source: Option<BoxedError>,
+    },
 }
 
 impl Error {
     pub fn new<T: Into<String>>(msg: T) -> Self {
-        Self { msg: msg.into() }
+        Self::Internal {
+            context: msg.into(),
+            source: None,
+        }
+    }
+
+    /// An internal error caused by another, such as a task that panicked.
+    pub fn internal<T: Into<String>>(
+        context: T,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::Internal {
+            context: context.into(),
+            source: Some(Box::new(source)),
+        }
+    }
+
+    pub fn config<T: Into<String>>(msg: T) -> Self {
+        Self::Config {
+            context: msg.into(),
+            source: None,
+        }
+    }
+
+    /// A configuration error found by another library, such as toml.
+    pub fn invalid_config<T: Into<String>>(
+        context: T,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::Config {
+            context: context.into(),
+            source: Some(Box::new(source)),
+        }
+    }
+
+    pub fn io<T: Into<String>>(context: T, source: std::io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// A parse error found by another library, such as serde.
+    pub fn parse<T: Into<String>>(
+        context: T,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::Parse {
+            context: context.into(),
+            source: Some(Box::new(source)),
+        }
+    }
+
+    /// A parse error found by our own checks.
+    pub fn invalid_text<T: Into<String>>(context: T) -> Self {
+        Self::Parse {
+            context: context.into(),
+            source: None,
+        }
+    }
+
+    /// The status to answer a request that failed with this error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::Validation(_) | Self::MissingPlayer { .. } | Self::Parse { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            Self::PlannerTimeout { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Config { .. } | Self::Io { .. } | Self::Internal { .. } => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    /// The error followed by each of its causes, for printing and logging.
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(cause) = source {
+            report.push_str(": ");
+            report.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        report
+    }
+
+    /// Logs the error, as a warning if it is the fault of whoever made the
+    /// request and as an error if it is ours.
+    pub fn log(&self) {
+        if self.status().is_client_error() {
+            warn!("{}", self.report());
+        } else {
+            error!("{}", self.report());
+        }
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(value: ValidationError) -> Self {
+        Self::Validation(value)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Validation(_) => f.write_str("Invalid request"),
+            Self::MissingPlayer { snake } => {
+                write!(f, "Could not find player snake \"{}\" on board.", snake)
+            }
+            Self::PlannerTimeout { elapsed, timeout } => write!(
+                f,
+                "Planning took {}ms, over the {}ms allowed.",
+                elapsed.as_millis(),
+                timeout.as_millis()
+            ),
+            Self::Config { context, .. }
+            | Self::Internal { context, .. }
+            | Self::Io { context, .. }
+            | Self::Parse { context, .. } => f.write_str(context),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Validation(e) => Some(e),
+            Self::Io { source, .. } => Some(source),
+            Self::Config {
+                source: Some(source),
+                ..
+            }
+            | Self::Internal {
+                source: Some(source),
+                ..
+            }
+            | Self::Parse {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            _ => None,
+        }
     }
 }
 
 impl axum::response::IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
-        (axum::http::StatusCode::INTERNAL_SERVER_ERROR, self.msg).into_response()
+        self.log();
+        (self.status(), self.report()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_report_their_causes() {
+        let error = Error::io(
+            "Could not read game.toml",
+            std::io::Error::new(std::io::ErrorKind::NotFound, "no such file"),
+        );
+        assert_eq!(error.report(), "Could not read game.toml: no such file");
+        assert_eq!(error.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let error: Error = ValidationError::TooManySnakes(9).into();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+        assert!(
+            error
+                .report()
+                .starts_with("Invalid request: Board has 9 snakes")
+        );
+
+        let error = Error::MissingPlayer {
+            snake: "you".into(),
+        };
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+
+        let error = Error::invalid_config(
+            "Could not parse configuration file game.toml",
+            "port = ".parse::<toml::Table>().unwrap_err(),
+        );
+        assert!(
+            error
+                .report()
+                .starts_with("Could not parse configuration file game.toml: TOML parse error")
+        );
     }
 }
@@ -0,0 +1,248 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use crate::{
+    error::{Error, Result},
+    game_state::{Battlesnake, Cell, GameState, MAX_BOARD_SIZE},
+};
+
+/// Labels enemies by their index in `GameState::enemies`, in upper case for
+/// the head and lower case for the body. The player is `Y` and `y`.
+static ENEMY_LABELS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWX";
+static PLAYER_LABEL: u8 = b'Y';
+static EMPTY: u8 = b'.';
+static FOOD: u8 = b'*';
+static HAZARD: u8 = b'#';
+
+/// Draws the board one character per cell with `y = 0` at the bottom, as on
+/// the official board:
+///
+/// ```text
+/// ..*..
+/// .Yy..
+/// ..y.#
+/// Aaa##
+/// ```
+///
+/// Snakes are drawn over food and food over hazards. Dead snakes and cells
+/// off the board are left out.
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut grid = vec![EMPTY; width * height];
+        let mut draw = |cell: Cell, c: u8| {
+            let Cell(x, y) = cell;
+            if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                grid[y as usize * width + x as usize] = c;
+            }
+        };
+        for &hazard in &self.hazards {
+            draw(hazard, HAZARD);
+        }
+        for &food in &self.food {
+            draw(food, FOOD);
+        }
+        let labelled = self
+            .enemies
+            .iter()
+            .enumerate()
+            .map(|(i, enemy)| (ENEMY_LABELS.get(i).copied().unwrap_or(b'?'), enemy))
+            .chain(std::iter::once((PLAYER_LABEL, &self.player)));
+        for (label, snake) in labelled {
+            for &cell in snake.body() {
+                draw(cell, label.to_ascii_lowercase());
+            }
+            if let Some(head) = snake.head() {
+                draw(head, label);
+            }
+        }
+        for row in grid.chunks(width.max(1)).rev() {
+            writeln!(f, "{}", String::from_utf8_lossy(row))?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a board drawn as by `Display`. Leading and trailing whitespace on
+/// each line and blank lines are ignored, so boards can be indented in
+/// tests. Each body is traced from the head through neighbouring cells of
+/// the same letter, which must leave no choice of which cell comes next.
+/// Stacked tails cannot be drawn.
+impl FromStr for GameState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rows: Vec<&[u8]> = s
+            .lines()
+            .map(|line| line.trim().as_bytes())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(Error::invalid_text(
+                "Every row of the board must be the same width.",
+            ));
+        }
+        if height.max(width) > MAX_BOARD_SIZE as usize {
+            return Err(Error::invalid_text(format!(
+                "The board is {}x{}, larger than the {} cells a side allowed.",
+                width, height, MAX_BOARD_SIZE
+            )));
+        }
+        let mut food = vec![];
+        let mut hazards = vec![];
+        let mut heads: HashMap<u8, Cell> = HashMap::new();
+        let mut bodies: HashMap<u8, Vec<Cell>> = HashMap::new();
+        for (i, row) in rows.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                let cell = Cell::at(x, height - 1 - i);
+                match c {
+                    _ if c == EMPTY => {}
+                    _ if c == FOOD => food.push(cell),
+                    _ if c == HAZARD => hazards.push(cell),
+                    _ if c == PLAYER_LABEL || ENEMY_LABELS.contains(&c) => {
+                        if heads.insert(c, cell).is_some() {
+                            return Err(Error::invalid_text(format!(
+                                "Snake {} has more than one head.",
+                                c as char
+                            )));
+                        }
+                    }
+                    _ if c == PLAYER_LABEL.to_ascii_lowercase()
+                        || ENEMY_LABELS.contains(&c.to_ascii_uppercase()) =>
+                    {
+                        bodies.entry(c.to_ascii_uppercase()).or_default().push(cell)
+                    }
+                    _ => {
+                        return Err(Error::invalid_text(format!(
+                            "Unknown board character '{}'.",
+                            c as char
+                        )));
+                    }
+                }
+            }
+        }
+        if let Some(&label) = bodies.keys().find(|label| !heads.contains_key(label)) {
+            return Err(Error::invalid_text(format!(
+                "Snake {} has a body but no head.",
+                label as char
+            )));
+        }
+        let mut trace = |label: u8| -> Result<Battlesnake> {
+            let Some(&head) = heads.get(&label) else {
+                return Ok(Battlesnake::new_dead());
+            };
+            let mut remaining = bodies.remove(&label).unwrap_or_default();
+            let mut cells = vec![head];
+            loop {
+                let Cell(x, y) = *cells.last().unwrap();
+                let next: Vec<usize> = (0..remaining.len())
+                    .filter(|&i| {
+                        let Cell(nx, ny) = remaining[i];
+                        (nx - x).abs() + (ny - y).abs() == 1
+                    })
+                    .collect();
+                match next.as_slice() {
+                    [] => break,
+                    &[i] => cells.push(remaining.swap_remove(i)),
+                    _ => {
+                        return Err(Error::invalid_text(format!(
+                            "The body of snake {} branches at ({}, {}).",
+                            label as char, x, y
+                        )));
+                    }
+                }
+            }
+            if !remaining.is_empty() {
+                return Err(Error::invalid_text(format!(
+                    "The body of snake {} is not connected to its head.",
+                    label as char
+                )));
+            }
+            let cells: Vec<_> = cells
+                .iter()
+                .map(|&Cell(x, y)| (x as usize, y as usize))
+                .collect();
+            Ok(Battlesnake::new(&cells))
+        };
+        let player = trace(PLAYER_LABEL)?;
+        let enemy_count = ENEMY_LABELS
+            .iter()
+            .rposition(|label| heads.contains_key(label))
+            .map_or(0, |i| i + 1);
+        let enemies = ENEMY_LABELS[..enemy_count]
+            .iter()
+            .map(|&label| trace(label))
+            .collect::<Result<_>>()?;
+        Ok(GameState {
+            height: height as u16,
+            width: width as u16,
+            player,
+            enemies,
+            food,
+            hazards,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_boards() {
+        let game_state: GameState = "
+            ..*..
+            .Yy..
+            ..y.#
+            .Bbb.
+            Aaa##
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(game_state.width, 5);
+        assert_eq!(game_state.height, 5);
+        assert_eq!(
+            game_state.player,
+            Battlesnake::new(&[(1, 3), (2, 3), (2, 2)])
+        );
+        assert_eq!(
+            game_state.enemies,
+            vec![
+                Battlesnake::new(&[(0, 0), (1, 0), (2, 0)]),
+                Battlesnake::new(&[(1, 1), (2, 1), (3, 1)]),
+            ]
+        );
+        assert_eq!(game_state.food, vec![Cell(2, 4)]);
+        assert_eq!(game_state.hazards, vec![Cell(4, 2), Cell(3, 0), Cell(4, 0)]);
+    }
+
+    #[test]
+    fn rendering_round_trips() {
+        let board = "..*..\n.Yy..\n..y.#\n.....\nAaa##\n";
+        let game_state: GameState = board.parse().unwrap();
+        assert_eq!(game_state.to_string(), board);
+    }
+
+    #[test]
+    fn missing_snakes_are_dead() {
+        let game_state: GameState = "B..\n...\n".parse().unwrap();
+        assert!(!game_state.player.is_alive());
+        assert_eq!(game_state.enemies.len(), 2);
+        assert!(!game_state.enemies[0].is_alive());
+        assert_eq!(game_state.enemies[1].head(), Some(Cell(0, 1)));
+    }
+
+    #[test]
+    fn malformed_boards_are_rejected() {
+        for board in [
+            "Yy.\n..", "Yy?\n...", "YyY\n...", "ay.\n...", "Yyy\nyy.", "Y.y\n...",
+        ] {
+            assert!(board.parse::<GameState>().is_err(), "{}", board);
+        }
+        let side = MAX_BOARD_SIZE as usize;
+        assert!(".".repeat(side + 1).parse::<GameState>().is_err());
+        assert!(".\n".repeat(side + 1).parse::<GameState>().is_err());
+        assert!(".\n".repeat(side).parse::<GameState>().is_ok());
+    }
+}
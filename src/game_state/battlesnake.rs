@@ -3,7 +3,7 @@ use crate::{
     game_state::{Cell, Move},
 };
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Battlesnake {
     cells: Vec<Cell>,
 }
@@ -22,10 +22,7 @@ impl From<&api_types::Battlesnake> for Battlesnake {
 
 impl Battlesnake {
     pub fn new(cells: &[(usize, usize)]) -> Self {
-        let cells = cells
-            .iter()
-            .map(|&(x, y)| Cell(x as i8, y as i8))
-            .collect();
+        let cells = cells.iter().map(|&(x, y)| Cell::at(x, y)).collect();
         Self { cells }
     }
     pub fn new_dead() -> Self {
@@ -33,17 +30,31 @@ impl Battlesnake {
     }
 
     pub fn update(&self, snake_move: Move, food: &[Cell]) -> Battlesnake {
-        let mut cells = Vec::with_capacity(self.cells.len());
+        let mut cells = Vec::with_capacity(self.cells.len() + 1);
         if let Some(&head) = self.cells.first() {
             let new_head = head + snake_move;
             cells.push(new_head);
-            cells.extend_from_slice(
-                &self.cells[0..self.cells.len() - if food.contains(&new_head) { 0 } else { 1 }],
-            );
+            cells.extend_from_slice(&self.cells[0..self.cells.len() - 1]);
+            if food.contains(&new_head) {
+                // Growing stacks a copy of the tail, which then stays put for a turn.
+                cells.push(*cells.last().unwrap());
+            }
         }
         Self { cells }
     }
 
+    /// Whether the snake will still be covering `cell` after its next move.
+    /// The tail moves out of the way unless it is stacked from having just
+    /// eaten.
+    pub fn occupies_next_turn(&self, cell: Cell) -> bool {
+        let moving_cells = match self.cells.as_slice() {
+            [.., a, b] if a == b => &self.cells[..],
+            [rest @ .., _] => rest,
+            [] => &[],
+        };
+        moving_cells.contains(&cell)
+    }
+
     pub fn length(&self) -> usize {
         self.cells.len()
     }
@@ -113,19 +124,19 @@ mod tests {
             cells: vec![Cell(2, 3), Cell(2, 4), Cell(2, 5), Cell(1, 5), Cell(0, 5)],
         };
         assert_eq!(
-            target.update(Move::Up, &vec![]).cells,
+            target.update(Move::Up, &[]).cells,
             vec![Cell(2, 4), Cell(2, 3), Cell(2, 4), Cell(2, 5), Cell(1, 5)]
         );
         assert_eq!(
-            target.update(Move::Down, &vec![]).cells,
+            target.update(Move::Down, &[]).cells,
             vec![Cell(2, 2), Cell(2, 3), Cell(2, 4), Cell(2, 5), Cell(1, 5)]
         );
         assert_eq!(
-            target.update(Move::Left, &vec![]).cells,
+            target.update(Move::Left, &[]).cells,
             vec![Cell(1, 3), Cell(2, 3), Cell(2, 4), Cell(2, 5), Cell(1, 5)]
         );
         assert_eq!(
-            target.update(Move::Right, &vec![]).cells,
+            target.update(Move::Right, &[]).cells,
             vec![Cell(3, 3), Cell(2, 3), Cell(2, 4), Cell(2, 5), Cell(1, 5)]
         );
 
@@ -133,23 +144,67 @@ mod tests {
             cells: vec![Cell(5, 5), Cell(4, 5)],
         };
         assert_eq!(
-            target.update(Move::Up, &vec![]).cells,
+            target.update(Move::Up, &[]).cells,
             vec![Cell(5, 6), Cell(5, 5)]
         );
         assert_eq!(
-            target.update(Move::Left, &vec![]).cells,
+            target.update(Move::Left, &[]).cells,
             vec![Cell(4, 5), Cell(5, 5)]
         );
 
         let target = Battlesnake {
             cells: vec![Cell(7, 0)],
         };
-        assert_eq!(target.update(Move::Up, &vec![]).cells, vec![Cell(7, 1)]);
-        assert_eq!(target.update(Move::Left, &vec![]).cells, vec![Cell(6, 0)]);
+        assert_eq!(target.update(Move::Up, &[]).cells, vec![Cell(7, 1)]);
+        assert_eq!(target.update(Move::Left, &[]).cells, vec![Cell(6, 0)]);
 
         let target = Battlesnake { cells: vec![] };
-        assert_eq!(target.update(Move::Up, &vec![]).cells, vec![]);
-        assert_eq!(target.update(Move::Left, &vec![]).cells, vec![]);
+        assert_eq!(target.update(Move::Up, &[]).cells, vec![]);
+        assert_eq!(target.update(Move::Left, &[]).cells, vec![]);
+    }
+
+    #[test]
+    fn update_battlesnake_eating() {
+        let target = Battlesnake {
+            cells: vec![Cell(2, 3), Cell(2, 4), Cell(2, 5)],
+        };
+        assert_eq!(
+            target.update(Move::Down, &[Cell(2, 2)]).cells,
+            vec![Cell(2, 2), Cell(2, 3), Cell(2, 4), Cell(2, 4)]
+        );
+        assert_eq!(
+            target
+                .update(Move::Down, &[Cell(2, 2)])
+                .update(Move::Down, &[])
+                .cells,
+            vec![Cell(2, 1), Cell(2, 2), Cell(2, 3), Cell(2, 4)]
+        );
+
+        let target = Battlesnake {
+            cells: vec![Cell(7, 0)],
+        };
+        assert_eq!(
+            target.update(Move::Up, &[Cell(7, 1)]).cells,
+            vec![Cell(7, 1), Cell(7, 1)]
+        );
+    }
+
+    #[test]
+    fn battlesnake_occupies_next_turn() {
+        let target = Battlesnake {
+            cells: vec![Cell(2, 3), Cell(2, 4), Cell(2, 5)],
+        };
+        assert!(target.occupies_next_turn(Cell(2, 3)));
+        assert!(target.occupies_next_turn(Cell(2, 4)));
+        assert!(!target.occupies_next_turn(Cell(2, 5)));
+        assert!(!target.occupies_next_turn(Cell(2, 6)));
+
+        let target = Battlesnake {
+            cells: vec![Cell(2, 3), Cell(2, 4), Cell(2, 5), Cell(2, 5)],
+        };
+        assert!(target.occupies_next_turn(Cell(2, 5)));
+
+        assert!(!Battlesnake::new_dead().occupies_next_turn(Cell(0, 0)));
     }
 
     #[test]
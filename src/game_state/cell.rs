@@ -1,10 +1,42 @@
 use crate::api_types;
 
+/// A coordinate on the board: wide enough for any board up to
+/// `MAX_BOARD_SIZE`, with room to spare for stepping off an edge.
+pub type Coord = i16;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct Cell(pub i8, pub i8);
+pub struct Cell(pub Coord, pub Coord);
+
+impl Cell {
+    /// The cell at `(x, y)`. Coordinates too large for a `Coord` become
+    /// `Coord::MAX`, which is off any board.
+    pub fn at(x: usize, y: usize) -> Self {
+        let coord = |v: usize| Coord::try_from(v).unwrap_or(Coord::MAX);
+        Self(coord(x), coord(y))
+    }
+}
 
 impl From<&api_types::Coordinates> for Cell {
+    /// Coordinates out of a `Coord`'s range are clamped to it, so that they
+    /// stay off the board rather than wrapping onto it.
     fn from(value: &api_types::Coordinates) -> Self {
-        Self(value.x as i8, value.y as i8)
+        let coord = |v: i32| v.clamp(Coord::MIN.into(), Coord::MAX.into()) as Coord;
+        Self(coord(value.x), coord(value.y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn out_of_range_coordinates_stay_off_the_board() {
+        let cell = |x, y| Cell::from(&api_types::Coordinates { x, y });
+        assert_eq!(cell(3, 7), Cell(3, 7));
+        assert_eq!(cell(i32::MAX, 0), Cell(Coord::MAX, 0));
+        assert_eq!(cell(0, i32::MIN), Cell(0, Coord::MIN));
+        assert_eq!(cell(1 << 16, 2), Cell(Coord::MAX, 2));
+        assert_eq!(Cell::at(usize::MAX, 5), Cell(Coord::MAX, 5));
+        assert_eq!(Cell::at(25, 24), Cell(25, 24));
     }
 }
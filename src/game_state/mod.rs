@@ -3,24 +3,36 @@ use crate::{
     error::{Error, Result},
 };
 
+mod ascii;
 mod battlesnake;
 pub use battlesnake::Battlesnake;
 mod movement;
 pub use movement::Move;
 mod cell;
-pub use cell::Cell;
+pub use cell::{Cell, Coord};
 
-#[derive(Debug)]
+/// The widest and tallest board accepted: ten times the official maps, and
+/// well within what a `Coord` can hold.
+pub static MAX_BOARD_SIZE: i32 = 250;
+/// The most snakes accepted on a board. The planner looks at every
+/// combination of enemy moves, so this bounds the work in each ply.
+pub static MAX_SNAKES: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct GameState {
     pub height: u16,
     pub width: u16,
     pub player: Battlesnake,
     pub enemies: Vec<Battlesnake>,
     pub food: Vec<Cell>,
+    pub hazards: Vec<Cell>,
 }
 
 impl GameState {
+    /// Converts a board from the game server, refusing any that fail
+    /// `Board::validate`, since the planner relies on the checks made there.
     pub fn from_board(board: &api_types::Board, player_id: &str) -> Result<Self> {
+        board.validate()?;
         let height = board.height as u16;
         let width = board.width as u16;
         let player = board
@@ -33,11 +45,8 @@ impl GameState {
                     None
                 }
             })
-            .ok_or_else(|| {
-                Error::new(format!(
-                    "Could not find player snake \"{}\" on board.",
-                    player_id
-                ))
+            .ok_or_else(|| Error::MissingPlayer {
+                snake: player_id.into(),
             })?;
         let enemies = board
             .snakes
@@ -51,20 +60,112 @@ impl GameState {
             })
             .collect();
         let food = board.food.iter().map(|coord| coord.into()).collect();
+        let hazards = board.hazards.iter().map(|coord| coord.into()).collect();
         Ok(GameState {
             height,
             width,
             player,
             enemies,
             food,
+            hazards,
         })
     }
 }
 
+impl GameState {
+    /// The moves that do not send `snake` off the board or into a cell that
+    /// will still be covered by a snake body after everyone has moved.
+    /// Head-on collisions are not considered.
+    pub fn legal_moves(&self, snake: &Battlesnake) -> Vec<Move> {
+        let Some(head) = snake.head() else {
+            return vec![];
+        };
+        Move::enumerate()
+            .filter(|&snake_move| {
+                let Cell(x, y) = head + snake_move;
+                x >= 0
+                    && y >= 0
+                    && (x as u16) < self.width
+                    && (y as u16) < self.height
+                    && !self.snakes().any(|s| s.occupies_next_turn(Cell(x, y)))
+            })
+            .collect()
+    }
+
+    /// The player followed by all the enemies.
+    pub fn snakes(&self) -> impl Iterator<Item = &Battlesnake> {
+        std::iter::once(&self.player).chain(self.enemies.iter())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn legal_moves_allows_tail_chasing() {
+        struct Case {
+            name: &'static str,
+            player: Battlesnake,
+            enemies: Vec<Battlesnake>,
+            expected: Vec<Move>,
+        }
+        let cases = [
+            Case {
+                name: "own tail is free",
+                player: Battlesnake::new(&[(2, 2), (2, 3), (3, 3), (3, 2)]),
+                enemies: vec![],
+                expected: vec![Move::Down, Move::Left, Move::Right],
+            },
+            Case {
+                name: "own stacked tail is occupied",
+                player: Battlesnake::new(&[(2, 2), (2, 3), (3, 3), (3, 2), (3, 2)]),
+                enemies: vec![],
+                expected: vec![Move::Down, Move::Left],
+            },
+            Case {
+                name: "enemy tail is free",
+                player: Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]),
+                enemies: vec![Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2)])],
+                expected: vec![Move::Up, Move::Down, Move::Right],
+            },
+            Case {
+                name: "enemy stacked tail is occupied",
+                player: Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]),
+                enemies: vec![Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2), (3, 2)])],
+                expected: vec![Move::Up, Move::Down],
+            },
+            Case {
+                name: "enemy body is occupied",
+                player: Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]),
+                enemies: vec![Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2), (4, 2)])],
+                expected: vec![Move::Up, Move::Down],
+            },
+            Case {
+                name: "board edges",
+                player: Battlesnake::new(&[(0, 0), (1, 0), (2, 0)]),
+                enemies: vec![],
+                expected: vec![Move::Up],
+            },
+        ];
+        for case in cases {
+            let game_state = GameState {
+                height: 11,
+                width: 11,
+                player: case.player,
+                enemies: case.enemies,
+                food: vec![],
+                hazards: vec![],
+            };
+            assert_eq!(
+                game_state.legal_moves(&game_state.player),
+                case.expected,
+                "{}",
+                case.name
+            );
+        }
+    }
+
     #[test]
     fn game_state_from_board() {
         let board_json = r##"{
@@ -154,4 +255,31 @@ mod tests {
         assert!(target.enemies.iter().any(|e| e.head() == Some(Cell(3, 3))));
         assert_eq!(target.food.len(), 3);
     }
+
+    #[test]
+    fn from_board_rejects_boards_it_cannot_represent() {
+        let board = |width: i32, snakes: usize, food_x: i32| -> api_types::Board {
+            let snake = |i: usize| {
+                serde_json::json!({
+                    "id": format!("snake-{}", i), "name": "", "health": 100,
+                    "body": [{"x": 0, "y": 0}], "latency": "0", "head": {"x": 0, "y": 0},
+                    "length": 1, "shout": "", "squad": "", "customizations": {}
+                })
+            };
+            serde_json::from_value(serde_json::json!({
+                "width": width, "height": 11,
+                "food": [{"x": food_x, "y": 3}], "hazards": [],
+                "snakes": (0..snakes).map(snake).collect::<Vec<_>>()
+            }))
+            .unwrap()
+        };
+        assert!(GameState::from_board(&board(11, 2, 10), "snake-0").is_ok());
+        assert!(GameState::from_board(&board(0, 2, 0), "snake-0").is_err());
+        assert!(GameState::from_board(&board(-5, 2, 0), "snake-0").is_err());
+        assert!(GameState::from_board(&board(MAX_BOARD_SIZE, 2, 0), "snake-0").is_ok());
+        assert!(GameState::from_board(&board(MAX_BOARD_SIZE + 1, 2, 0), "snake-0").is_err());
+        assert!(GameState::from_board(&board(11, 2, 11), "snake-0").is_err());
+        assert!(GameState::from_board(&board(11, 2, -1), "snake-0").is_err());
+        assert!(GameState::from_board(&board(11, MAX_SNAKES + 1, 0), "snake-0").is_err());
+    }
 }
@@ -1,6 +1,9 @@
+use serde::{Deserialize, Serialize};
+
 use crate::game_state::Cell;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Move {
     Up,
     Down,
@@ -39,12 +42,13 @@ impl Iterator for MovesEnumerator {
 impl std::ops::Add<Move> for Cell {
     type Output = Cell;
 
+    /// Saturates at the ends of `Coord`'s range, which are off any board.
     fn add(self, rhs: Move) -> Self::Output {
         match rhs {
-            Move::Up => Cell(self.0, self.1 + 1),
-            Move::Down => Cell(self.0, self.1 - 1),
-            Move::Left => Cell(self.0 - 1, self.1),
-            Move::Right => Cell(self.0 + 1, self.1),
+            Move::Up => Cell(self.0, self.1.saturating_add(1)),
+            Move::Down => Cell(self.0, self.1.saturating_sub(1)),
+            Move::Left => Cell(self.0.saturating_sub(1), self.1),
+            Move::Right => Cell(self.0.saturating_add(1), self.1),
         }
     }
 }
@@ -52,6 +56,7 @@ impl std::ops::Add<Move> for Cell {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::game_state::Coord;
 
     #[test]
     fn add_move_to_cell() {
@@ -65,6 +70,15 @@ mod tests {
         assert_eq!(Cell(3, 7) + Move::Right, Cell(4, 7));
     }
 
+    #[test]
+    fn add_move_to_cell_saturates() {
+        assert_eq!(Cell(Coord::MAX, 0) + Move::Right, Cell(Coord::MAX, 0));
+        assert_eq!(Cell(0, Coord::MAX) + Move::Up, Cell(0, Coord::MAX));
+        assert_eq!(Cell(Coord::MIN, 0) + Move::Left, Cell(Coord::MIN, 0));
+        assert_eq!(Cell(0, Coord::MIN) + Move::Down, Cell(0, Coord::MIN));
+        assert_eq!(Cell(Coord::MAX, 0) + Move::Left, Cell(Coord::MAX - 1, 0));
+    }
+
     #[test]
     fn moves_enumerator() {
         let result: Vec<_> = Move::enumerate().collect();
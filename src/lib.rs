@@ -1,5 +1,16 @@
 pub mod api_types;
+pub mod config;
+pub mod decision_log;
+pub mod engine;
 pub mod error;
 pub mod game_state;
+pub mod metrics;
 pub mod planner;
+pub mod puzzle;
+pub mod recording;
+pub mod session;
+pub mod shout;
+pub mod tournament;
+pub mod tuning;
+pub mod validation;
 
@@ -1,9 +1,169 @@
-use {tower_http::trace::TraceLayer, tracing_subscriber::EnvFilter};
+use {
+    blaze_haskell::{
+        config::{CONFIG_FILE_VAR, Config},
+        error::{Error, Result},
+        planner::Strategy,
+    },
+    clap::{Parser, Subcommand},
+    futures_util::future::{Either, select},
+    log::{info, warn},
+    std::{future::IntoFuture, net::IpAddr, path::PathBuf, time::Duration},
+    tokio::{
+        signal::unix::{SignalKind, signal},
+        sync::oneshot,
+        time::{Instant, timeout_at},
+    },
+    tower_http::trace::TraceLayer,
+    tracing_subscriber::EnvFilter,
+};
 
+mod plan_command;
+mod play_command;
 mod router;
+mod tournament_command;
+mod tune_command;
 
-#[tokio::main]
-async fn main() {
+/// How often a draining server checks whether its games have ended.
+static DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A Battlesnake server.
+///
+/// Settings are read from the configuration file, then from
+/// BLAZE_HASKELL_<TABLE>_<KEY> environment variables (e.g.
+/// BLAZE_HASKELL_SERVER_PORT), then from the options below, each overriding
+/// the last.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// TOML configuration file [env: BLAZE_HASKELL_CONFIG]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long)]
+    address: Option<IpAddr>,
+    /// Port to listen on
+    #[arg(long)]
+    port: Option<u16>,
+    /// Snake color, as a hex code like "#00ff00"
+    #[arg(long)]
+    color: Option<String>,
+    /// Snake head customization
+    #[arg(long)]
+    head: Option<String>,
+    /// Snake tail customization
+    #[arg(long)]
+    tail: Option<String>,
+    /// Say what the planner is up to in each move's shout
+    #[arg(long)]
+    shout: bool,
+    /// Deepest search, in turns
+    #[arg(long, global = true)]
+    search_depth: Option<usize>,
+    /// How to treat the enemies' moves
+    #[arg(long, global = true, value_parser = parse_strategy)]
+    strategy: Option<Strategy>,
+    /// Hunt shorter snakes
+    #[arg(long, global = true)]
+    aggressive: bool,
+    /// Milliseconds of each move's time limit to leave for network latency
+    #[arg(long)]
+    latency_margin_ms: Option<u64>,
+    /// Directory to write a JSON lines log of every move's decision to
+    #[arg(long)]
+    decision_log_dir: Option<PathBuf>,
+    /// Directory to record every game played to
+    #[arg(long)]
+    recording_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the planner on a single board instead of serving
+    Plan(plan_command::PlanArgs),
+    /// Play local games between snakes by the standard rules
+    Play(play_command::PlayArgs),
+    /// Play many seeded games between planner settings and compare them
+    Tournament(tournament_command::TournamentArgs),
+    /// Tune the planner's weights by self-play
+    Tune(tune_command::TuneArgs),
+}
+
+impl Cli {
+    fn overrides(&self) -> toml::Table {
+        let mut overrides = toml::Table::new();
+        let mut set = |table: &str, key: &str, value: toml::Value| {
+            overrides
+                .entry(table)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .unwrap()
+                .insert(key.into(), value);
+        };
+        if let Some(address) = self.address {
+            set("server", "address", address.to_string().into());
+        }
+        if let Some(port) = self.port {
+            set("server", "port", i64::from(port).into());
+        }
+        if let Some(color) = &self.color {
+            set("snake", "color", color.clone().into());
+        }
+        if let Some(head) = &self.head {
+            set("snake", "head", head.clone().into());
+        }
+        if let Some(tail) = &self.tail {
+            set("snake", "tail", tail.clone().into());
+        }
+        if self.shout {
+            set("snake", "shout", true.into());
+        }
+        if let Some(search_depth) = self.search_depth {
+            set("planner", "search_depth", (search_depth as i64).into());
+        }
+        if let Some(strategy) = self.strategy {
+            set(
+                "planner",
+                "strategy",
+                toml::Value::try_from(strategy).unwrap(),
+            );
+        }
+        if self.aggressive {
+            set("planner", "aggressive", true.into());
+        }
+        if let Some(latency_margin_ms) = self.latency_margin_ms {
+            set(
+                "timing",
+                "latency_margin_ms",
+                (latency_margin_ms as i64).into(),
+            );
+        }
+        if let Some(decision_log_dir) = &self.decision_log_dir {
+            set(
+                "logging",
+                "decision_log_dir",
+                decision_log_dir.display().to_string().into(),
+            );
+        }
+        if let Some(recording_dir) = &self.recording_dir {
+            set(
+                "logging",
+                "recording_dir",
+                recording_dir.display().to_string().into(),
+            );
+        }
+        overrides
+    }
+}
+
+fn parse_strategy(value: &str) -> std::result::Result<Strategy, String> {
+    toml::Value::String(value.into())
+        .try_into()
+        .map_err(|_| format!("unknown strategy \"{}\"", value))
+}
+
+fn main() {
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::try_from_default_env()
@@ -11,8 +171,114 @@ async fn main() {
                 .unwrap(),
         )
         .init();
-    let app = router::router().layer(TraceLayer::new_for_http());
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let cli = Cli::parse();
+    let config_file = cli
+        .config
+        .clone()
+        .or_else(|| std::env::var_os(CONFIG_FILE_VAR).map(PathBuf::from));
+    let config = match Config::load(config_file.as_deref(), std::env::vars(), cli.overrides()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e.report());
+            std::process::exit(2);
+        }
+    };
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!(
+                "{}",
+                Error::io("Could not start the async runtime", e).report()
+            );
+            std::process::exit(1);
+        }
+    };
+    let result = runtime.block_on(async {
+        match &cli.command {
+            Some(Command::Plan(args)) => plan_command::run(args, &config.planner),
+            Some(Command::Play(args)) => play_command::run(args, &config).await,
+            Some(Command::Tournament(args)) => tournament_command::run(args, &config),
+            Some(Command::Tune(args)) => tune_command::run(args, &config.planner),
+            None => serve(&config).await,
+        }
+    });
+    // Don't wait on searches still running for requests abandoned by a drain
+    // that timed out; there is no one left to answer.
+    runtime.shutdown_background();
+    if let Err(e) = result {
+        eprintln!("{}", e.report());
+        std::process::exit(1);
+    }
+}
+
+/// Serves until SIGTERM or SIGINT, then turns down new games while the games
+/// in progress finish, giving them and the game logs still being written
+/// `drain_timeout_ms` in all.
+async fn serve(config: &Config) -> Result<()> {
+    let lifecycle = router::Lifecycle::default();
+    let app = router::router(config, &lifecycle).layer(TraceLayer::new_for_http());
+    let mut terminate =
+        signal(SignalKind::terminate()).map_err(|e| Error::io("Could not handle SIGTERM", e))?;
+    let mut interrupt =
+        signal(SignalKind::interrupt()).map_err(|e| Error::io("Could not handle SIGINT", e))?;
+
+    let listener = tokio::net::TcpListener::bind((config.server.address, config.server.port))
+        .await
+        .map_err(|e| {
+            Error::io(
+                format!(
+                    "Could not listen on {}:{}",
+                    config.server.address, config.server.port
+                ),
+                e,
+            )
+        })?;
+    lifecycle.set_ready(true);
+
+    let (stop, stopped) = oneshot::channel::<()>();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        let _ = stopped.await;
+    });
+    let mut server = tokio::spawn(server.into_future());
+    let signalled = select(Box::pin(terminate.recv()), Box::pin(interrupt.recv()));
+    let drain_timeout = Duration::from_millis(config.server.drain_timeout_ms);
+    // One deadline covers the games, the requests in flight and the logs.
+    let (served, deadline) = match select(signalled, &mut server).await {
+        Either::Left(_) => {
+            let deadline = Instant::now() + drain_timeout;
+            lifecycle.set_ready(false);
+            info!(
+                "Shutting down; waiting up to {}ms for the games in progress.",
+                drain_timeout.as_millis()
+            );
+            let games_ended = async {
+                while lifecycle.live_games() > 0 {
+                    tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+                }
+            };
+            if timeout_at(deadline, games_ended).await.is_err() {
+                warn!("Games were still in progress after the drain timeout.");
+            }
+            let _ = stop.send(());
+            let served = match timeout_at(deadline, &mut server).await {
+                Ok(served) => served,
+                Err(_) => {
+                    warn!("Requests were still in flight after the drain timeout.");
+                    server.abort();
+                    Ok(Ok(()))
+                }
+            };
+            (served, deadline)
+        }
+        Either::Right((served, _)) => (served, Instant::now() + drain_timeout),
+    };
+
+    if timeout_at(deadline, lifecycle.flush_logs()).await.is_err() {
+        warn!("Game logs were still being written after the drain timeout.");
+    }
+    served
+        .map_err(|e| Error::internal("The server failed", e))?
+        .map_err(|e| Error::io("Server failed", e))
 }
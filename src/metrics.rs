@@ -0,0 +1,250 @@
+use {
+    prometheus_client::{
+        encoding::{EncodeLabelSet, EncodeLabelValue, LabelValueEncoder, text::encode},
+        metrics::{
+            counter::Counter,
+            family::Family,
+            histogram::{Histogram, exponential_buckets},
+        },
+        registry::Registry,
+    },
+    std::{fmt, time::Duration},
+};
+
+use crate::{api_types::Board, planner::Plan};
+
+/// The content type of `Metrics::encode`.
+pub static CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestLabels {
+    route: String,
+    status: u16,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct SnakeLabels {
+    snake: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct GameLabels {
+    snake: String,
+    result: GameResult,
+}
+
+/// How a game ended for the player.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum GameResult {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl GameResult {
+    /// Reads the result off the final board sent with `/end`, where only the
+    /// surviving snakes are left.
+    pub fn from_board(board: &Board, player_id: &str) -> Self {
+        let survived = board.snakes.iter().any(|snake| snake.id == player_id);
+        match board.snakes.len() {
+            0 => GameResult::Draw,
+            1 if survived => GameResult::Win,
+            _ if survived => GameResult::Draw,
+            _ => GameResult::Loss,
+        }
+    }
+}
+
+impl EncodeLabelValue for GameResult {
+    fn encode(&self, encoder: &mut LabelValueEncoder) -> Result<(), fmt::Error> {
+        let value = match self {
+            GameResult::Win => "win",
+            GameResult::Loss => "loss",
+            GameResult::Draw => "draw",
+        };
+        EncodeLabelValue::encode(&value, encoder)
+    }
+}
+
+type HistogramFamily = Family<SnakeLabels, Histogram, fn() -> Histogram>;
+
+/// Everything the server reports on `/metrics`, shared by all snakes.
+#[derive(Debug)]
+pub struct Metrics {
+    registry: Registry,
+    requests: Family<RequestLabels, Counter>,
+    move_duration: HistogramFamily,
+    search_depth: HistogramFamily,
+    nodes_searched: HistogramFamily,
+    reported_latency: HistogramFamily,
+    games: Family<GameLabels, Counter>,
+    fallback_moves: Family<SnakeLabels, Counter>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let mut registry = Registry::with_prefix("blaze_haskell");
+        let requests = Family::<RequestLabels, Counter>::default();
+        registry.register("requests", "Requests served, by route", requests.clone());
+        let move_duration: HistogramFamily =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(0.005, 2.0, 10)));
+        registry.register(
+            "move_duration_seconds",
+            "Time taken to answer /move",
+            move_duration.clone(),
+        );
+        let search_depth: HistogramFamily =
+            Family::new_with_constructor(|| Histogram::new((1..=8).map(f64::from)));
+        registry.register(
+            "search_depth",
+            "Deepest search finished on each move, in turns",
+            search_depth.clone(),
+        );
+        let nodes_searched: HistogramFamily =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(10.0, 4.0, 10)));
+        registry.register(
+            "nodes_searched",
+            "Search tree nodes visited on each move",
+            nodes_searched.clone(),
+        );
+        let reported_latency: HistogramFamily =
+            Family::new_with_constructor(|| Histogram::new(exponential_buckets(10.0, 2.0, 9)));
+        registry.register(
+            "reported_latency_milliseconds",
+            "Latency of our previous move as reported by the game engine",
+            reported_latency.clone(),
+        );
+        let games = Family::<GameLabels, Counter>::default();
+        registry.register("games", "Games finished, by result", games.clone());
+        let fallback_moves = Family::<SnakeLabels, Counter>::default();
+        registry.register(
+            "fallback_moves",
+            "Moves answered without a plan, because the planner failed or ran out of time",
+            fallback_moves.clone(),
+        );
+        Self {
+            registry,
+            requests,
+            move_duration,
+            search_depth,
+            nodes_searched,
+            reported_latency,
+            games,
+            fallback_moves,
+        }
+    }
+
+    pub fn record_request(&self, route: &str, status: u16) {
+        self.requests
+            .get_or_create(&RequestLabels {
+                route: route.into(),
+                status,
+            })
+            .inc();
+    }
+
+    /// Records how long a move took to answer, and how far the search got
+    /// if it produced a `plan` rather than falling back.
+    pub fn record_move(&self, snake: &str, duration: Duration, plan: Option<&Plan>) {
+        let labels = SnakeLabels {
+            snake: snake.into(),
+        };
+        self.move_duration
+            .get_or_create(&labels)
+            .observe(duration.as_secs_f64());
+        if let Some(plan) = plan {
+            self.search_depth
+                .get_or_create(&labels)
+                .observe(plan.search_depth as f64);
+            self.nodes_searched
+                .get_or_create(&labels)
+                .observe(plan.nodes_searched as f64);
+        }
+    }
+
+    pub fn record_fallback(&self, snake: &str) {
+        self.fallback_moves
+            .get_or_create(&SnakeLabels {
+                snake: snake.into(),
+            })
+            .inc();
+    }
+
+    /// Records the latency the engine reports in `Battlesnake::latency`. It
+    /// is sent as a string, which is empty on the first turn and is skipped
+    /// whenever it isn't a number.
+    pub fn record_reported_latency(&self, snake: &str, latency: &str) {
+        if let Ok(latency) = latency.trim().parse::<f64>() {
+            self.reported_latency
+                .get_or_create(&SnakeLabels {
+                    snake: snake.into(),
+                })
+                .observe(latency);
+        }
+    }
+
+    pub fn record_game(&self, snake: &str, result: GameResult) {
+        self.games
+            .get_or_create(&GameLabels {
+                snake: snake.into(),
+                result,
+            })
+            .inc();
+    }
+
+    /// Renders every metric in the OpenMetrics text format.
+    pub fn encode(&self) -> String {
+        let mut buffer = String::new();
+        encode(&mut buffer, &self.registry).unwrap();
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::Move;
+
+    #[test]
+    fn encodes_recorded_metrics() {
+        let metrics = Metrics::new();
+        metrics.record_request("/move", 200);
+        metrics.record_request("/move", 200);
+        metrics.record_move(
+            "default",
+            Duration::from_millis(40),
+            Some(&Plan {
+                player_move: Move::Up,
+                score: 0.0,
+                move_scores: vec![(Move::Up, 0.0)],
+                search_depth: 3,
+                nodes_searched: 120,
+            }),
+        );
+        metrics.record_move("default", Duration::from_millis(480), None);
+        metrics.record_reported_latency("default", "");
+        metrics.record_reported_latency("default", "87");
+        metrics.record_game("default", GameResult::Win);
+        metrics.record_fallback("default");
+
+        let text = metrics.encode();
+        assert!(text.contains(r#"blaze_haskell_requests_total{route="/move",status="200"} 2"#));
+        assert!(text.contains(r#"blaze_haskell_move_duration_seconds_count{snake="default"} 2"#));
+        assert!(text.contains(r#"blaze_haskell_search_depth_count{snake="default"} 1"#));
+        assert!(text.contains(r#"blaze_haskell_nodes_searched_sum{snake="default"} 120.0"#));
+        assert!(
+            text.contains(
+                r#"blaze_haskell_reported_latency_milliseconds_count{snake="default"} 1"#
+            )
+        );
+        assert!(text.contains(r#"blaze_haskell_games_total{snake="default",result="win"} 1"#));
+        assert!(text.contains(r#"blaze_haskell_fallback_moves_total{snake="default"} 1"#));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
@@ -0,0 +1,157 @@
+use {
+    clap::Args,
+    std::{
+        path::{Path, PathBuf},
+        time::{Duration, Instant},
+    },
+};
+
+use blaze_haskell::{
+    api_types::{MoveRequest, MoveResponse},
+    error::{Error, Result},
+    game_state::{GameState, Move},
+    planner::{OpponentModel, PlannerConfig, SearchTree},
+    puzzle::Puzzle,
+    recording::Recording,
+    shout,
+};
+
+/// Prints the move the planner would pick for a recorded board, and the
+/// score of each move it considered.
+#[derive(Args)]
+pub struct PlanArgs {
+    /// A JSON `/move` request, or a game recording if --turn is given
+    input: PathBuf,
+    /// Which turn of the recording to plan
+    #[arg(long)]
+    turn: Option<u32>,
+    /// Stop deepening the search after this many milliseconds [default: search
+    /// to the full depth]
+    #[arg(long)]
+    time_budget_ms: Option<u64>,
+    /// Save the board as a planner puzzle at this path, expecting the
+    /// recorded move (or, without one, the move picked)
+    #[arg(long)]
+    puzzle: Option<PathBuf>,
+}
+
+pub fn run(args: &PlanArgs, config: &PlannerConfig) -> Result<()> {
+    let (request, recorded, opponent_model) = match args.turn {
+        Some(turn) => load_recorded_turn(args, turn)?,
+        None => {
+            let contents = std::fs::read_to_string(&args.input)
+                .map_err(|e| Error::io(format!("Could not read {}", args.input.display()), e))?;
+            let request: MoveRequest = serde_json::from_str(&contents).map_err(|e| {
+                Error::parse(
+                    format!("Could not parse move request {}", args.input.display()),
+                    e,
+                )
+            })?;
+            (request, None, OpponentModel::default())
+        }
+    };
+    let game_state = GameState::from_board(&request.board, &request.you.id)?;
+    let opponents = opponent_model.behaviours(&request.board, &request.you.id);
+
+    let start = Instant::now();
+    let deadline = args
+        .time_budget_ms
+        .map(|ms| start + Duration::from_millis(ms));
+    let plan = SearchTree::new(game_state.clone()).find_plan(config, &opponents, deadline);
+    let elapsed = start.elapsed();
+
+    println!("Turn {} of game {}", request.turn, request.game.id);
+    println!(
+        "Searched {} turns deep, {} nodes in {:.1} ms",
+        plan.search_depth,
+        plan.nodes_searched,
+        elapsed.as_secs_f64() * 1000.0
+    );
+    for &(player_move, score) in &plan.move_scores {
+        let marker = if player_move == plan.player_move {
+            "  <- chosen"
+        } else {
+            ""
+        };
+        println!("  {:<6} {:>8.3}{}", move_name(player_move), score, marker);
+    }
+    if let Some(shout) = shout::shout(&request, &game_state, &plan, config) {
+        println!("Shout: {}", shout);
+    }
+    let recorded_move = recorded.map(|recorded| Move::from(recorded.move_direction));
+    if let Some(recorded_move) = recorded_move {
+        println!("Recorded move: {}", move_name(recorded_move));
+    }
+    if let Some(path) = &args.puzzle {
+        let expected = recorded_move.unwrap_or(plan.player_move);
+        save_puzzle(path, &request, &game_state, expected, config)?;
+        println!(
+            "Wrote {}; check its description and expected moves.",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Writes the board as a puzzle expecting `expected`. The board is drawn if
+/// it can be, and otherwise (e.g. with stacked tails, or health that
+/// matters) saved as the JSON request beside the puzzle.
+fn save_puzzle(
+    path: &Path,
+    request: &MoveRequest,
+    game_state: &GameState,
+    expected: Move,
+    planner: &PlannerConfig,
+) -> Result<()> {
+    let write = |path: &Path, contents: String| {
+        std::fs::write(path, contents)
+            .map_err(|e| Error::io(format!("Could not write {}", path.display()), e))
+    };
+    let drawn = game_state.to_string();
+    let drawable = drawn.parse::<GameState>().ok().as_ref() == Some(game_state);
+    let (board, request_file) = if drawable {
+        (Some(drawn), None)
+    } else {
+        let request_path = path.with_extension("json");
+        let json = serde_json::to_string_pretty(request)
+            .map_err(|e| Error::parse("Could not write the move request", e))?;
+        write(&request_path, json + "\n")?;
+        (None, request_path.file_name().map(PathBuf::from))
+    };
+    let puzzle = Puzzle {
+        description: format!("Turn {} of game {}", request.turn, request.game.id),
+        expected: vec![expected],
+        forbidden: vec![],
+        planner: *planner,
+        board,
+        request: request_file,
+    };
+    let contents =
+        toml::to_string(&puzzle).map_err(|e| Error::parse("Could not write the puzzle", e))?;
+    write(path, contents)
+}
+
+/// Finds `turn` in the recording at `args.input`, along with what the
+/// opponent model would have learned from the turns before it.
+fn load_recorded_turn(
+    args: &PlanArgs,
+    turn: u32,
+) -> Result<(MoveRequest, Option<MoveResponse>, OpponentModel)> {
+    let recording = Recording::load(&args.input)?;
+    let mut opponent_model = OpponentModel::default();
+    for recorded in recording.turns {
+        opponent_model.observe(&recorded.request.board);
+        if recorded.request.turn == turn {
+            return Ok((recorded.request, Some(recorded.response), opponent_model));
+        }
+    }
+    Err(Error::config(format!(
+        "{} has no move for turn {}",
+        args.input.display(),
+        turn
+    )))
+}
+
+fn move_name(player_move: Move) -> String {
+    format!("{:?}", player_move).to_lowercase()
+}
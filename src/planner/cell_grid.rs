@@ -0,0 +1,135 @@
+use crate::game_state::Cell;
+
+pub struct CellGrid<T> {
+    width: usize,
+    height: usize,
+    data: Vec<T>,
+}
+
+impl<T: Default + Clone> CellGrid<T> {
+    pub fn new(width: usize, height: usize) -> Self {
+        let data = vec![T::default(); width * height];
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+}
+
+impl<T> CellGrid<T> {
+    pub fn contains(&self, cell: Cell) -> bool {
+        self.offset(cell).is_some()
+    }
+
+    /// The contents of `cell`, or `None` if it is off the grid.
+    pub fn get(&self, cell: Cell) -> Option<&T> {
+        self.offset(cell).map(|i| &self.data[i])
+    }
+
+    pub fn get_mut(&mut self, cell: Cell) -> Option<&mut T> {
+        self.offset(cell).map(|i| &mut self.data[i])
+    }
+
+    fn offset(&self, Cell(x, y): Cell) -> Option<usize> {
+        let x = usize::try_from(x).ok().filter(|&x| x < self.width)?;
+        let y = usize::try_from(y).ok().filter(|&y| y < self.height)?;
+        Some(y * self.width + x)
+    }
+}
+
+/// Indexing panics on cells off the grid; use `get` where they may be.
+impl<T> std::ops::Index<Cell> for CellGrid<T> {
+    type Output = T;
+
+    fn index(&self, cell: Cell) -> &Self::Output {
+        match self.get(cell) {
+            Some(contents) => contents,
+            None => panic!("{:?} is off the {}x{} grid", cell, self.width, self.height),
+        }
+    }
+}
+
+impl<T> std::ops::IndexMut<Cell> for CellGrid<T> {
+    fn index_mut(&mut self, cell: Cell) -> &mut Self::Output {
+        let (width, height) = (self.width, self.height);
+        match self.get_mut(cell) {
+            Some(contents) => contents,
+            None => panic!("{:?} is off the {}x{} grid", cell, width, height),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::Coord;
+
+    #[test]
+    fn cell_grid_read_empty() {
+        let target: CellGrid<bool> = CellGrid::new(13, 9);
+        for x in 0..13 {
+            for y in 0..9 {
+                assert!(!target[Cell(x, y)]);
+            }
+        }
+    }
+
+    #[test]
+    fn cell_grid_oob_panic() {
+        let target: CellGrid<bool> = CellGrid::new(13, 9);
+        let result = std::panic::catch_unwind(|| target[Cell(13, 2)]);
+        assert!(result.is_err());
+        let result = std::panic::catch_unwind(|| target[Cell(2, 9)]);
+        assert!(result.is_err());
+        let result = std::panic::catch_unwind(|| target[Cell(-1, 3)]);
+        assert!(result.is_err());
+        let result = std::panic::catch_unwind(|| target[Cell(2, -1)]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn cell_grid_write_read() {
+        let mut target: CellGrid<(Coord, Coord)> = CellGrid::new(7, 9);
+        for x in 0..7 {
+            for y in 0..9 {
+                target[Cell(x, y)] = (x, y);
+            }
+        }
+        for x in 0..7 {
+            for y in 0..9 {
+                assert_eq!(target[Cell(x, y)], (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn cell_grid_get_handles_every_edge() {
+        let mut target: CellGrid<bool> = CellGrid::new(250, 250);
+        *target.get_mut(Cell(249, 249)).unwrap() = true;
+        assert_eq!(target.get(Cell(249, 249)), Some(&true));
+        assert_eq!(target.get(Cell(0, 0)), Some(&false));
+        for cell in [
+            Cell(250, 0),
+            Cell(0, 250),
+            Cell(-1, 0),
+            Cell(0, -1),
+            Cell(Coord::MAX, Coord::MAX),
+            Cell(Coord::MIN, Coord::MIN),
+        ] {
+            assert_eq!(target.get(cell), None, "{:?}", cell);
+            assert!(target.get_mut(cell).is_none(), "{:?}", cell);
+        }
+    }
+
+    #[test]
+    fn cell_grid_contains() {
+        let target: CellGrid<bool> = CellGrid::new(7, 9);
+        assert!(target.contains(Cell(0, 0)));
+        assert!(target.contains(Cell(6, 8)));
+        assert!(!target.contains(Cell(7, 8)));
+        assert!(!target.contains(Cell(6, 9)));
+        assert!(!target.contains(Cell(-1, 0)));
+        assert!(!target.contains(Cell(0, -1)));
+    }
+}
@@ -1,64 +1,62 @@
-use crate::game_state::{Battlesnake, Cell, GameState};
+use crate::{
+    game_state::{Battlesnake, Cell, GameState},
+    planner::cell_grid::CellGrid,
+};
 
 pub fn check_collisions(game_state: GameState) -> GameState {
     let mut cells = CellGrid::new(game_state.width as usize, game_state.height as usize);
 
-    for &cell in game_state.player.body() {
-        cells[cell] = CellContents::PlayerBody;
-    }
-    for snake in &game_state.enemies {
-        for &cell in snake.body() {
-            cells[cell] = CellContents::EnemySnakeBody;
+    let mut fill = |body: &[Cell], contents| {
+        for &cell in body {
+            if let Some(cell) = cells.get_mut(cell) {
+                *cell = contents;
+            }
         }
+    };
+    fill(game_state.player.body(), CellContents::PlayerBody);
+    for snake in &game_state.enemies {
+        fill(snake.body(), CellContents::EnemySnakeBody);
     }
 
-    let mut player = if let Some(cell) = game_state.player.head() {
-        match cells[cell] {
-            CellContents::Empty => game_state.player,
-            CellContents::PlayerBody => Battlesnake::new_dead(),
-            CellContents::EnemySnakeBody => Battlesnake::new_dead(),
+    // Head-on collisions are judged before any snake is removed, so that both
+    // snakes die when they are the same length, whichever comes first.
+    let heads: Vec<_> = game_state
+        .snakes()
+        .map(|snake| (snake.head(), snake.length()))
+        .collect();
+    let survives = |i: usize, snake: &Battlesnake| {
+        let Some(head) = snake.head() else {
+            return true;
+        };
+        // A head off the board counts as a hit, though it is normally
+        // removed before collisions are checked.
+        let hits_body = cells.get(head) != Some(&CellContents::Empty);
+        let loses_head_on = heads
+            .iter()
+            .enumerate()
+            .any(|(j, &(other, length))| j != i && other == Some(head) && length >= snake.length());
+        !hits_body && !loses_head_on
+    };
+    let survivors: Vec<_> = game_state
+        .snakes()
+        .enumerate()
+        .map(|(i, snake)| survives(i, snake))
+        .collect();
+    let check = |snake: Battlesnake, survived: bool| {
+        if survived {
+            snake
+        } else {
+            Battlesnake::new_dead()
         }
-    } else {
-        game_state.player
     };
-    let mut enemies: Vec<Battlesnake> = game_state
+    let player = check(game_state.player, survivors[0]);
+    let enemies = game_state
         .enemies
         .into_iter()
-        .filter_map(|snake| {
-            if let Some(cell) = snake.head() {
-                match cells[cell] {
-                    CellContents::Empty => Some(snake),
-                    CellContents::PlayerBody => None,
-                    CellContents::EnemySnakeBody => None,
-                }
-            } else {
-                Some(snake)
-            }
-        })
+        .zip(&survivors[1..])
+        .map(|(snake, &survived)| check(snake, survived))
         .collect();
 
-    let num_enemies = enemies.len();
-    for i in 0..num_enemies {
-        if enemies[i].is_alive() && enemies[i].head() == player.head() {
-            if enemies[i].length() >= player.length() {
-                player = Battlesnake::new_dead()
-            }
-            if enemies[i].length() <= player.length() {
-                enemies[i] = Battlesnake::new_dead()
-            }
-        }
-        for j in i + 1..num_enemies {
-            if enemies[j].is_alive() && enemies[i].head() == enemies[j].head() {
-                if enemies[i].length() >= enemies[j].length() {
-                    enemies[j] = Battlesnake::new_dead()
-                }
-                if enemies[i].length() <= enemies[j].length() {
-                    enemies[i] = Battlesnake::new_dead()
-                }
-            }
-        }
-    }
-
     GameState {
         player,
         enemies,
@@ -74,79 +72,10 @@ enum CellContents {
     EnemySnakeBody,
 }
 
-struct CellGrid<T> {
-    width: usize,
-    data: Vec<T>,
-}
-
-impl<T: Default + Clone> CellGrid<T> {
-    pub fn new(width: usize, height: usize) -> Self {
-        let data = vec![T::default(); width * height];
-        Self { data, width }
-    }
-}
-
-impl<T> std::ops::Index<Cell> for CellGrid<T> {
-    type Output = T;
-
-    fn index(&self, Cell(x, y): Cell) -> &Self::Output {
-        assert!(x >= 0);
-        assert!(y >= 0);
-        assert!((x as usize) < self.width);
-        &self.data[y as usize * self.width + x as usize]
-    }
-}
-
-impl<T> std::ops::IndexMut<Cell> for CellGrid<T> {
-    fn index_mut(&mut self, Cell(x, y): Cell) -> &mut Self::Output {
-        assert!(x >= 0);
-        assert!(y >= 0);
-        assert!((x as usize) < self.width);
-        &mut self.data[y as usize * self.width + x as usize]
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn cell_grid_read_empty() {
-        let target: CellGrid<CellContents> = CellGrid::new(13, 9);
-        for x in 0..13 {
-            for y in 0..9 {
-                assert_eq!(target[Cell(x, y)], CellContents::Empty);
-            }
-        }
-    }
-
-    #[test]
-    fn cell_grid_oob_panic() {
-        let target: CellGrid<CellContents> = CellGrid::new(13, 9);
-        let result = std::panic::catch_unwind(|| target[Cell(13, 2)]);
-        assert!(result.is_err());
-        let result = std::panic::catch_unwind(|| target[Cell(2, 9)]);
-        assert!(result.is_err());
-        let result = std::panic::catch_unwind(|| target[Cell(-1, 3)]);
-        assert!(result.is_err());
-        let result = std::panic::catch_unwind(|| target[Cell(2, -1)]);
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn cell_grid_write_read() {
-        let mut target: CellGrid<(i8, i8)> = CellGrid::new(7, 9);
-        for x in 0..7 {
-            for y in 0..9 {
-                target[Cell(x, y)] = (x, y);
-            }
-        }
-        for x in 0..7 {
-            for y in 0..9 {
-                assert_eq!(target[Cell(x, y)], (x, y));
-            }
-        }
-    }
+    use crate::game_state::{Cell, Move};
 
     #[test]
     fn test_player_collision_detected() {
@@ -159,6 +88,7 @@ mod tests {
                 Battlesnake::new(&[(1, 3), (2, 3), (3, 3), (4, 3), (5, 3)]),
             ],
             food: vec![],
+            hazards: vec![],
         };
         let new_gamestate = check_collisions(gamestate);
         assert!(!new_gamestate.player.is_alive());
@@ -173,10 +103,167 @@ mod tests {
                 Battlesnake::new(&[(7, 2), (7, 3), (8, 3)]),
             ],
             food: vec![],
+            hazards: vec![],
         };
         let new_gamestate = check_collisions(gamestate);
         assert!(!new_gamestate.player.is_alive());
         assert!(new_gamestate.enemies[0].is_alive());
         assert!(new_gamestate.enemies[1].is_alive());
     }
+
+    #[test]
+    fn dead_enemies_keep_their_place() {
+        let gamestate = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (3, 2), (3, 1), (3, 0)]),
+            enemies: vec![
+                Battlesnake::new(&[(3, 1), (4, 1), (5, 1)]),
+                Battlesnake::new(&[(7, 2), (7, 3), (8, 3)]),
+            ],
+            food: vec![],
+            hazards: vec![],
+        };
+        let new_gamestate = check_collisions(gamestate);
+        assert!(new_gamestate.player.is_alive());
+        assert_eq!(new_gamestate.enemies.len(), 2);
+        assert!(!new_gamestate.enemies[0].is_alive());
+        assert_eq!(new_gamestate.enemies[1].head(), Some(Cell(7, 2)));
+    }
+
+    #[test]
+    fn equal_head_on_collisions_kill_both() {
+        let gamestate = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(5, 5), (4, 5), (3, 5)]),
+            enemies: vec![
+                Battlesnake::new(&[(8, 8), (8, 7)]),
+                Battlesnake::new(&[(5, 5), (6, 5), (7, 5)]),
+                Battlesnake::new(&[(5, 5), (5, 6), (5, 7)]),
+            ],
+            food: vec![],
+            hazards: vec![],
+        };
+        let new_gamestate = check_collisions(gamestate);
+        assert!(!new_gamestate.player.is_alive());
+        assert!(new_gamestate.enemies[0].is_alive());
+        assert!(!new_gamestate.enemies[1].is_alive());
+        assert!(!new_gamestate.enemies[2].is_alive());
+    }
+
+    #[test]
+    fn tail_chasing() {
+        struct Case {
+            name: &'static str,
+            player: Battlesnake,
+            player_move: Move,
+            enemies: Vec<(Battlesnake, Move)>,
+            food: Vec<Cell>,
+            player_survives: bool,
+            enemies_survive: bool,
+        }
+        let cases = [
+            Case {
+                name: "player chases own tail",
+                player: Battlesnake::new(&[(2, 2), (2, 3), (3, 3), (3, 2)]),
+                player_move: Move::Right,
+                enemies: vec![],
+                food: vec![],
+                player_survives: true,
+                enemies_survive: true,
+            },
+            Case {
+                name: "player chases own stacked tail",
+                player: Battlesnake::new(&[(2, 2), (2, 3), (3, 3), (3, 2), (3, 2)]),
+                player_move: Move::Right,
+                enemies: vec![],
+                food: vec![],
+                player_survives: false,
+                enemies_survive: true,
+            },
+            Case {
+                name: "player chases enemy tail",
+                player: Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]),
+                player_move: Move::Right,
+                enemies: vec![(
+                    Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2)]),
+                    Move::Up,
+                )],
+                food: vec![],
+                player_survives: true,
+                enemies_survive: true,
+            },
+            Case {
+                name: "player chases enemy stacked tail",
+                player: Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]),
+                player_move: Move::Right,
+                enemies: vec![(
+                    Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2), (3, 2)]),
+                    Move::Up,
+                )],
+                food: vec![],
+                player_survives: false,
+                enemies_survive: true,
+            },
+            Case {
+                name: "player chases tail of enemy that is eating",
+                player: Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]),
+                player_move: Move::Right,
+                enemies: vec![(
+                    Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2)]),
+                    Move::Up,
+                )],
+                food: vec![Cell(4, 5)],
+                player_survives: true,
+                enemies_survive: true,
+            },
+            Case {
+                name: "enemy chases player tail",
+                player: Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2)]),
+                player_move: Move::Up,
+                enemies: vec![(Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]), Move::Right)],
+                food: vec![],
+                player_survives: true,
+                enemies_survive: true,
+            },
+            Case {
+                name: "enemy chases player stacked tail",
+                player: Battlesnake::new(&[(4, 4), (4, 3), (3, 3), (3, 2), (3, 2)]),
+                player_move: Move::Up,
+                enemies: vec![(Battlesnake::new(&[(2, 2), (1, 2), (0, 2)]), Move::Right)],
+                food: vec![],
+                player_survives: true,
+                enemies_survive: false,
+            },
+        ];
+        for case in cases {
+            let gamestate = GameState {
+                height: 11,
+                width: 11,
+                player: case.player.update(case.player_move, &case.food),
+                enemies: case
+                    .enemies
+                    .iter()
+                    .map(|(snake, snake_move)| snake.update(*snake_move, &case.food))
+                    .collect(),
+                food: case.food.clone(),
+                hazards: vec![],
+            };
+            let new_gamestate = check_collisions(gamestate);
+            assert_eq!(
+                new_gamestate.player.is_alive(),
+                case.player_survives,
+                "{}",
+                case.name
+            );
+            assert_eq!(new_gamestate.enemies.len(), case.enemies.len());
+            assert_eq!(
+                new_gamestate.enemies.iter().all(|e| e.is_alive()),
+                case.enemies_survive,
+                "{}",
+                case.name
+            );
+        }
+    }
 }
@@ -0,0 +1,98 @@
+use crate::{game_state::GameState, planner::cell_grid::CellGrid};
+
+/// What the player risks by moving its head into a cell on the next turn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HeadToHeadRisk {
+    #[default]
+    None,
+    /// Only enemies shorter than the player can reach the cell, so meeting one
+    /// there would kill it.
+    Opportunity,
+    /// An enemy at least as long as the player can reach the cell.
+    Danger,
+}
+
+/// Builds a map of the cells that enemy heads can legally reach next turn,
+/// graded by whether a head-on collision there would go for or against the
+/// player.
+pub fn head_to_head_risk(game_state: &GameState) -> CellGrid<HeadToHeadRisk> {
+    let mut risk: CellGrid<HeadToHeadRisk> =
+        CellGrid::new(game_state.width as usize, game_state.height as usize);
+    let player_length = game_state.player.length();
+    for enemy in &game_state.enemies {
+        if let Some(head) = enemy.head() {
+            let enemy_risk = if enemy.length() >= player_length {
+                HeadToHeadRisk::Danger
+            } else {
+                HeadToHeadRisk::Opportunity
+            };
+            for cell in game_state.legal_moves(enemy).into_iter().map(|m| head + m) {
+                if let Some(risk) = risk.get_mut(cell) {
+                    *risk = (*risk).max(enemy_risk);
+                }
+            }
+        }
+    }
+    risk
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::{Battlesnake, Cell};
+
+    #[test]
+    fn head_to_head_risk_grades_by_length() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(5, 5), (5, 4), (5, 3)]),
+            enemies: vec![
+                Battlesnake::new(&[(7, 5), (8, 5), (9, 5)]),
+                Battlesnake::new(&[(5, 7), (5, 8)]),
+            ],
+            food: vec![],
+            hazards: vec![],
+        };
+        let risk = head_to_head_risk(&game_state);
+        assert_eq!(risk[Cell(6, 5)], HeadToHeadRisk::Danger);
+        assert_eq!(risk[Cell(7, 6)], HeadToHeadRisk::Danger);
+        assert_eq!(risk[Cell(5, 6)], HeadToHeadRisk::Opportunity);
+        assert_eq!(risk[Cell(4, 7)], HeadToHeadRisk::Opportunity);
+        assert_eq!(risk[Cell(4, 5)], HeadToHeadRisk::None);
+        assert_eq!(risk[Cell(5, 5)], HeadToHeadRisk::None);
+    }
+
+    #[test]
+    fn head_to_head_danger_outranks_opportunity() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(0, 0), (0, 1), (0, 2)]),
+            enemies: vec![
+                Battlesnake::new(&[(4, 5), (3, 5)]),
+                Battlesnake::new(&[(6, 5), (7, 5), (8, 5), (9, 5)]),
+            ],
+            food: vec![],
+            hazards: vec![],
+        };
+        let risk = head_to_head_risk(&game_state);
+        assert_eq!(risk[Cell(5, 5)], HeadToHeadRisk::Danger);
+        assert_eq!(risk[Cell(4, 6)], HeadToHeadRisk::Opportunity);
+    }
+
+    #[test]
+    fn head_to_head_risk_only_counts_legal_moves() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(5, 5), (5, 4), (5, 3)]),
+            enemies: vec![Battlesnake::new(&[(0, 10), (1, 10), (2, 10)])],
+            food: vec![],
+            hazards: vec![],
+        };
+        let risk = head_to_head_risk(&game_state);
+        assert_eq!(risk[Cell(0, 9)], HeadToHeadRisk::Danger);
+        assert_eq!(risk[Cell(1, 10)], HeadToHeadRisk::None);
+    }
+}
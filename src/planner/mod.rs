@@ -1,14 +1,310 @@
-use crate::game_state::{Battlesnake, Cell, GameState, Move};
+use {
+    log::warn,
+    serde::{Deserialize, Serialize},
+    std::{
+        panic::{self, AssertUnwindSafe},
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::{Duration, Instant},
+    },
+};
 
+use crate::{
+    api_types::MoveRequest,
+    error::{Error, Result},
+    game_state::{Battlesnake, Cell, GameState, Move},
+    session::SessionRegistry,
+};
+
+mod cell_grid;
 mod check_collisions;
 use check_collisions::check_collisions;
+mod head_to_head;
+pub use head_to_head::HeadToHeadRisk;
+use head_to_head::head_to_head_risk;
+mod opponent_model;
+#[cfg(test)]
+mod proptests;
+use opponent_model::move_index;
+pub use opponent_model::{Behaviour, OpponentModel};
 
 static MAX_SEARCH_DEPTH: usize = 2;
+/// The most nodes of a search tree kept between turns.
+static MAX_RETAINED_NODES: usize = 250_000;
 static WIN_VALUE: f32 = 5.0;
 static LOSE_VALUE: f32 = -20.0;
+static HEAD_TO_HEAD_PENALTY: f32 = 10.0;
+static HUNT_BONUS: f32 = 2.0;
+static FOOD_VALUE: f32 = 10.0;
+static NO_FOOD_VALUE: f32 = 0.1;
+static DISTANCE_PENALTY: f32 = 0.1;
+
+/// How the planner treats the enemies' moves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Average over the enemies' moves, weighted by the opponent model.
+    #[default]
+    Expectimax,
+    /// Assume the enemies always make the moves that are worst for us.
+    Paranoid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlannerConfig {
+    /// The deepest the planner searches, in turns, if time allows.
+    pub search_depth: usize,
+    pub strategy: Strategy,
+    /// Seek out head-on collisions with shorter snakes rather than merely
+    /// avoiding those with longer ones.
+    pub aggressive: bool,
+    pub weights: Weights,
+}
+
+impl Default for PlannerConfig {
+    fn default() -> Self {
+        Self {
+            search_depth: MAX_SEARCH_DEPTH,
+            strategy: Strategy::default(),
+            aggressive: false,
+            weights: Weights::default(),
+        }
+    }
+}
+
+/// How the planner scores the states it searches.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weights {
+    /// The score of a state in which every enemy is dead.
+    pub win: f32,
+    /// The score of a state in which the player is dead.
+    pub lose: f32,
+    /// Taken off the score of a move that risks a head-on collision with a
+    /// snake at least as long.
+    pub head_to_head_penalty: f32,
+    /// When aggressive, added to the score of a move that threatens a head-on
+    /// collision with a shorter snake, and the most a state gets for being
+    /// close to one.
+    pub hunt_bonus: f32,
+    /// The most a state gets for being close to food.
+    pub food: f32,
+    /// The score of a state with no food on the board.
+    pub no_food: f32,
+    /// How much the food and hunt scores drop for each cell further away the
+    /// food or prey is.
+    pub distance_penalty: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            win: WIN_VALUE,
+            lose: LOSE_VALUE,
+            head_to_head_penalty: HEAD_TO_HEAD_PENALTY,
+            hunt_bonus: HUNT_BONUS,
+            food: FOOD_VALUE,
+            no_food: NO_FOOD_VALUE,
+            distance_penalty: DISTANCE_PENALTY,
+        }
+    }
+}
+
+impl Weights {
+    /// Each weight with its name in the configuration, in a fixed order.
+    pub fn named(&self) -> [(&'static str, f32); 7] {
+        [
+            ("win", self.win),
+            ("lose", self.lose),
+            ("head_to_head_penalty", self.head_to_head_penalty),
+            ("hunt_bonus", self.hunt_bonus),
+            ("food", self.food),
+            ("no_food", self.no_food),
+            ("distance_penalty", self.distance_penalty),
+        ]
+    }
+
+    /// The weights with the given values, in the order of `named`.
+    pub fn from_values(
+        [
+            win,
+            lose,
+            head_to_head_penalty,
+            hunt_bonus,
+            food,
+            no_food,
+            distance_penalty,
+        ]: [f32; 7],
+    ) -> Self {
+        Self {
+            win,
+            lose,
+            head_to_head_penalty,
+            hunt_bonus,
+            food,
+            no_food,
+            distance_penalty,
+        }
+    }
+}
+
+/// How a tree is searched for a plan, given the deadline to aim for and a
+/// flag that is set when the search should stop at once. This is
+/// `SearchTree::find_cancellable_plan` except in tests of what happens when
+/// the search goes wrong.
+pub type SearchFn =
+    fn(&mut SearchTree, &PlannerConfig, &[Behaviour], Option<Instant>, &AtomicBool) -> Option<Plan>;
 
-pub async fn devise_plan(game_state: GameState) -> Move {
-    find_plan(&game_state, MAX_SEARCH_DEPTH).0
+/// What came of planning one turn.
+#[derive(Debug)]
+pub struct Turn {
+    pub player_move: Move,
+    /// `None` if the planner failed or overran, and `player_move` is the
+    /// fallback.
+    pub plan: Option<Plan>,
+    pub duration: Duration,
+}
+
+/// Plans the player's move in `request`, whose board is `game_state`, picking
+/// up the game's session in `sessions` and leaving it ready for the next
+/// turn. The search aims to finish `latency_margin` before the game's time
+/// limit, counted from `start`, and is abandoned for `fallback_move` if it
+/// fails or is still going halfway through the margin.
+pub async fn plan_turn(
+    sessions: &SessionRegistry,
+    request: &MoveRequest,
+    game_state: GameState,
+    config: PlannerConfig,
+    latency_margin: Duration,
+    start: Instant,
+    search: SearchFn,
+) -> Turn {
+    let timeout = Duration::from_millis(request.game.timeout.max(0) as u64);
+    let deadline = start + timeout.saturating_sub(latency_margin);
+    let hard_deadline = start + timeout.saturating_sub(latency_margin / 2);
+    let fallback = fallback_move(&game_state);
+    // A panic here falls back just like one in the search.
+    let prepared = panic::catch_unwind(AssertUnwindSafe(|| {
+        sessions.with_session(&request.game.id, |session| {
+            session.prepare_search(&request.board, &request.you.id, game_state)
+        })
+    }));
+    let planned = match prepared {
+        Ok((tree, opponents)) => {
+            devise_plan(tree, config, opponents, deadline, hard_deadline, search).await
+        }
+        Err(_) => Err(Error::new("Preparing the search panicked")),
+    };
+    let duration = start.elapsed();
+    let (tree, planned) = match planned {
+        Ok((tree, plan)) => (Some(tree), Ok(plan)),
+        Err(e) => (None, Err(e)),
+    };
+    // The game may have ended while the search ran, in which case the tree
+    // is dropped rather than bringing its session back.
+    sessions.with_existing_session(&request.game.id, |session| {
+        session.move_durations.push(duration);
+        if tree.is_some() {
+            session.search_tree = tree;
+        }
+    });
+    match planned {
+        Ok(plan) => Turn {
+            player_move: plan.player_move,
+            plan: Some(plan),
+            duration,
+        },
+        Err(e) => {
+            e.log();
+            warn!(
+                "Falling back to {:?} on turn {} of game {}.",
+                fallback, request.turn, request.game.id
+            );
+            Turn {
+                player_move: fallback,
+                plan: None,
+                duration,
+            }
+        }
+    }
+}
+
+/// Searches `tree` with `search` on a blocking thread, aiming to finish by
+/// `deadline`, and hands the tree back, trimmed to keep between turns, along
+/// with the plan. Fails if the search panics or has not finished by
+/// `hard_deadline`, in which case the search is told to stop and the tree is
+/// lost.
+pub async fn devise_plan(
+    mut tree: SearchTree,
+    config: PlannerConfig,
+    opponents: Vec<Behaviour>,
+    deadline: Instant,
+    hard_deadline: Instant,
+    search: SearchFn,
+) -> Result<(SearchTree, Plan)> {
+    let start = Instant::now();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let task = tokio::task::spawn_blocking({
+        let cancelled = cancelled.clone();
+        move || {
+            let plan = search(&mut tree, &config, &opponents, Some(deadline), &cancelled)?;
+            tree.trim(MAX_RETAINED_NODES);
+            Some((tree, plan))
+        }
+    });
+    let timed_out = || Error::PlannerTimeout {
+        elapsed: start.elapsed(),
+        timeout: hard_deadline.saturating_duration_since(start),
+    };
+    match tokio::time::timeout_at(hard_deadline.into(), task).await {
+        Ok(Ok(Some(searched))) => Ok(searched),
+        Ok(Ok(None)) => Err(timed_out()),
+        Ok(Err(e)) => Err(Error::internal("The planner failed", e)),
+        Err(_) => {
+            cancelled.store(true, Ordering::Relaxed);
+            Err(timed_out())
+        }
+    }
+}
+
+/// A move to make when there is no plan: one that doesn't leave the board or
+/// run into a body, preferably away from any cell an enemy at least as long
+/// could also move into. Takes no time to speak of.
+pub fn fallback_move(game_state: &GameState) -> Move {
+    let player = &game_state.player;
+    let legal = game_state.legal_moves(player);
+    let contested = |cell: Cell| {
+        game_state.enemies.iter().any(|enemy| {
+            enemy.length() >= player.length()
+                && enemy.head().is_some_and(|head| {
+                    game_state
+                        .legal_moves(enemy)
+                        .into_iter()
+                        .any(|enemy_move| head + enemy_move == cell)
+                })
+        })
+    };
+    let uncontested = player
+        .head()
+        .and_then(|head| legal.iter().copied().find(|&m| !contested(head + m)));
+    uncontested.or(legal.first().copied()).unwrap_or(Move::Up)
+}
+
+/// The planner's choice of move and what it found along the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub player_move: Move,
+    pub score: f32,
+    /// The score of each move the player considered, from the deepest
+    /// finished search.
+    pub move_scores: Vec<(Move, f32)>,
+    /// How many turns ahead the deepest finished search looked.
+    pub search_depth: usize,
+    /// How many tree nodes were searched, counting every depth.
+    pub nodes_searched: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,36 +314,348 @@ enum GameStatus {
     Continue,
 }
 
-pub fn find_plan(game_state: &GameState, search_depth: usize) -> (Move, f32) {
-    if search_depth > 0 {
-        Move::enumerate()
-            .map(|player_move| {
-                (
-                    player_move,
-                    combine_scores(
-                        get_possible_next_states(game_state, player_move)
-                            .map(check_out_of_bounds)
-                            .map(check_collisions)
-                            .map(|new_game_state| match check_win_lose(&new_game_state) {
-                                GameStatus::Win => WIN_VALUE,
-                                GameStatus::Lose => LOSE_VALUE,
-                                GameStatus::Continue => {
-                                    find_plan(&new_game_state, search_depth - 1).1
-                                }
-                            }),
-                    ),
-                )
+/// Picks the player's best move. `opponents` gives the expected behaviour of
+/// each enemy, in order; enemies without an entry are assumed to move at
+/// random.
+pub fn find_plan(
+    game_state: &GameState,
+    config: &PlannerConfig,
+    opponents: &[Behaviour],
+) -> (Move, f32) {
+    let plan = SearchTree::new(game_state.clone()).find_plan(config, opponents, None);
+    (plan.player_move, plan.score)
+}
+
+/// The game tree explored by the planner, kept so that the part of it that
+/// comes true can be searched further on the next turn.
+#[derive(Debug)]
+pub struct SearchTree {
+    root: Node,
+    /// For each enemy in the real game, the index of the same snake among the
+    /// tree's enemies, which also include those that have since died.
+    enemy_indices: Vec<usize>,
+}
+
+impl SearchTree {
+    pub fn new(game_state: GameState) -> Self {
+        Self {
+            enemy_indices: (0..game_state.enemies.len()).collect(),
+            root: Node::new(game_state),
+        }
+    }
+
+    /// Searches one turn deeper at a time until reaching the configured depth
+    /// or running out of time, and returns the result of the deepest search
+    /// that finished. A one-turn search always runs to completion.
+    pub fn find_plan(
+        &mut self,
+        config: &PlannerConfig,
+        opponents: &[Behaviour],
+        deadline: Option<Instant>,
+    ) -> Plan {
+        self.find_cancellable_plan(config, opponents, deadline, &AtomicBool::new(false))
+            .expect("only cancelled searches stop short")
+    }
+
+    /// Like `find_plan`, but gives up, even on the one-turn search, as soon
+    /// as `cancelled` is set. Returns `None` if it gives up before finishing
+    /// a one-turn search.
+    pub fn find_cancellable_plan(
+        &mut self,
+        config: &PlannerConfig,
+        opponents: &[Behaviour],
+        deadline: Option<Instant>,
+        cancelled: &AtomicBool,
+    ) -> Option<Plan> {
+        let mut tree_opponents = vec![Behaviour::default(); self.root.game_state.enemies.len()];
+        for (&index, &behaviour) in self.enemy_indices.iter().zip(opponents) {
+            tree_opponents[index] = behaviour;
+        }
+        let mut search = Search {
+            config,
+            opponents: &tree_opponents,
+            deadline: None,
+            cancelled,
+            nodes_searched: 0,
+        };
+        let max_depth = config.search_depth.max(1);
+        let first_depth = if deadline.is_some() { 1 } else { max_depth };
+        let mut move_scores = self.root.score_moves(&mut search, first_depth)?;
+        let mut search_depth = first_depth;
+        search.deadline = deadline;
+        for deeper in first_depth + 1..=max_depth {
+            match self.root.score_moves(&mut search, deeper) {
+                Some(scores) => (move_scores, search_depth) = (scores, deeper),
+                None => break,
+            }
+        }
+        let (player_move, score) = best_move(&move_scores);
+        Some(Plan {
+            player_move,
+            score,
+            move_scores,
+            search_depth,
+            nodes_searched: search.nodes_searched,
+        })
+    }
+
+    /// Moves the root down to the outcome of the last turn that matches
+    /// `observed`, keeping everything already explored below it. Returns
+    /// `None` if no explored outcome matches, e.g. because food has spawned.
+    pub fn advance(self, observed: &GameState) -> Option<Self> {
+        let root = self
+            .root
+            .branches?
+            .into_iter()
+            .flat_map(|branch| branch.outcomes)
+            .find(|node| node.is_realized_as(observed))?;
+        let enemy_indices = root
+            .game_state
+            .enemies
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.is_alive())
+            .map(|(i, _)| i)
+            .collect();
+        Some(Self {
+            root,
+            enemy_indices,
+        })
+    }
+
+    /// Forgets the deepest levels of the tree until it has at most
+    /// `max_nodes` nodes.
+    pub fn trim(&mut self, max_nodes: usize) {
+        let mut level_sizes = Vec::new();
+        self.root.count_levels(0, &mut level_sizes);
+        let mut total = 0;
+        let levels = level_sizes
+            .iter()
+            .take_while(|&&size| {
+                total += size;
+                total <= max_nodes
             })
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-            .unwrap()
-    } else {
-        (Move::Up, heuristic_score(game_state))
+            .count();
+        if levels < level_sizes.len() {
+            self.root.truncate(levels.saturating_sub(1));
+        }
+    }
+}
+
+/// What stays the same throughout one search.
+struct Search<'a> {
+    config: &'a PlannerConfig,
+    /// In the order of the tree's enemies.
+    opponents: &'a [Behaviour],
+    deadline: Option<Instant>,
+    cancelled: &'a AtomicBool,
+    nodes_searched: usize,
+}
+
+impl Search<'_> {
+    /// Counts a node as searched. Returns `None` if the deadline has passed
+    /// or the search has been cancelled.
+    fn visit(&mut self) -> Option<()> {
+        self.nodes_searched += 1;
+        if self.cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => None,
+            _ => Some(()),
+        }
     }
 }
 
-fn combine_scores(scores: impl ExactSizeIterator<Item = f32>) -> f32 {
-    let count = scores.len() as f32;
-    scores.sum::<f32>() / count
+#[derive(Debug)]
+struct Node {
+    game_state: GameState,
+    /// The outcomes of each of the player's moves, filled in the first time
+    /// the node is searched.
+    branches: Option<Vec<Branch>>,
+}
+
+#[derive(Debug)]
+struct Branch {
+    player_move: Move,
+    risk: HeadToHeadRisk,
+    /// In the order of `get_possible_next_states`.
+    outcomes: Vec<Node>,
+}
+
+impl Node {
+    fn new(game_state: GameState) -> Self {
+        Self {
+            game_state,
+            branches: None,
+        }
+    }
+
+    /// The value of this node to the player. Returns `None` if the deadline
+    /// passes before the search is done.
+    fn search(&mut self, search: &mut Search, search_depth: usize) -> Option<f32> {
+        if search_depth > 0 {
+            let move_scores = self.score_moves(search, search_depth)?;
+            Some(best_move(&move_scores).1)
+        } else {
+            search.visit()?;
+            Some(heuristic_score(&self.game_state, search.config))
+        }
+    }
+
+    /// Scores each of the player's moves by searching `search_depth` turns,
+    /// which must be at least one.
+    fn score_moves(
+        &mut self,
+        search: &mut Search,
+        search_depth: usize,
+    ) -> Option<Vec<(Move, f32)>> {
+        search.visit()?;
+        let config = search.config;
+        let game_state = &self.game_state;
+        let branches = self
+            .branches
+            .get_or_insert_with(|| expand(game_state, config));
+        let probabilities: Vec<_> =
+            get_outcome_probabilities(game_state, search.opponents).collect();
+        let mut move_scores = Vec::with_capacity(branches.len());
+        for branch in branches.iter_mut() {
+            let mut scores = Vec::with_capacity(branch.outcomes.len());
+            for node in branch.outcomes.iter_mut() {
+                scores.push(match check_win_lose(&node.game_state) {
+                    GameStatus::Win => config.weights.win,
+                    GameStatus::Lose => config.weights.lose,
+                    GameStatus::Continue => node.search(search, search_depth - 1)?,
+                });
+            }
+            let score = match config.strategy {
+                Strategy::Expectimax => combine_scores(probabilities.iter().copied().zip(scores)),
+                Strategy::Paranoid => scores.into_iter().fold(f32::INFINITY, f32::min),
+            };
+            let adjustment = match branch.risk {
+                HeadToHeadRisk::Danger => -config.weights.head_to_head_penalty,
+                HeadToHeadRisk::Opportunity if config.aggressive => config.weights.hunt_bonus,
+                _ => 0.0,
+            };
+            move_scores.push((branch.player_move, score + adjustment));
+        }
+        Some(move_scores)
+    }
+
+    /// Adds the number of nodes at each level of this subtree to
+    /// `level_sizes`, this node being at `level`.
+    fn count_levels(&self, level: usize, level_sizes: &mut Vec<usize>) {
+        if level_sizes.len() <= level {
+            level_sizes.push(0);
+        }
+        level_sizes[level] += 1;
+        for node in self.children() {
+            node.count_levels(level + 1, level_sizes);
+        }
+    }
+
+    /// Forgets everything more than `levels` turns below this node.
+    fn truncate(&mut self, levels: usize) {
+        if levels == 0 {
+            self.branches = None;
+        } else {
+            for branch in self.branches.iter_mut().flatten() {
+                for node in &mut branch.outcomes {
+                    node.truncate(levels - 1);
+                }
+            }
+        }
+    }
+
+    fn children(&self) -> impl Iterator<Item = &Node> {
+        self.branches
+            .iter()
+            .flatten()
+            .flat_map(|branch| &branch.outcomes)
+    }
+
+    /// Whether this simulated state is what actually happened, given the
+    /// state the game server reports. The server leaves out dead snakes.
+    /// Food spawning or hazards moving, which the simulation can't foresee,
+    /// mean it didn't.
+    fn is_realized_as(&self, observed: &GameState) -> bool {
+        let simulated = &self.game_state;
+        simulated.width == observed.width
+            && simulated.height == observed.height
+            && simulated.player == observed.player
+            && simulated
+                .enemies
+                .iter()
+                .filter(|s| s.is_alive())
+                .eq(observed.enemies.iter())
+            && simulated.food.len() == observed.food.len()
+            && simulated.food.iter().all(|f| observed.food.contains(f))
+            && simulated.hazards.len() == observed.hazards.len()
+            && simulated
+                .hazards
+                .iter()
+                .all(|h| observed.hazards.contains(h))
+    }
+}
+
+fn expand(game_state: &GameState, config: &PlannerConfig) -> Vec<Branch> {
+    let risk = head_to_head_risk(game_state);
+    let move_risk = |player_move: Move| {
+        game_state
+            .player
+            .head()
+            .map(|head| head + player_move)
+            .filter(|&cell| risk.contains(cell))
+            .map(|cell| risk[cell])
+            .unwrap_or_default()
+    };
+    let mut moves = game_state.legal_moves(&game_state.player);
+    if moves.is_empty() {
+        moves = Move::enumerate().collect();
+    }
+    let mut branches: Vec<_> = moves
+        .into_iter()
+        .map(|player_move| Branch {
+            player_move,
+            risk: move_risk(player_move),
+            outcomes: get_possible_next_states(game_state, player_move)
+                .map(check_out_of_bounds)
+                .map(check_collisions)
+                .map(Node::new)
+                .collect(),
+        })
+        .collect();
+    // Ties go to the earliest move, so put the safest (or, when hunting, the
+    // most aggressive) moves first.
+    branches.sort_by_key(|branch| match branch.risk {
+        HeadToHeadRisk::Opportunity if config.aggressive => 0,
+        HeadToHeadRisk::None | HeadToHeadRisk::Opportunity => 1,
+        HeadToHeadRisk::Danger => 2,
+    });
+    branches
+}
+
+/// The first of the highest scoring moves.
+fn best_move(move_scores: &[(Move, f32)]) -> (Move, f32) {
+    move_scores
+        .iter()
+        .copied()
+        .reduce(|best, next| if next.1 > best.1 { next } else { best })
+        .unwrap()
+}
+
+/// The expected score over outcomes given as `(probability, score)` pairs.
+fn combine_scores(scores: impl Iterator<Item = (f32, f32)>) -> f32 {
+    let (total_probability, total_score) = scores.fold(
+        (0.0, 0.0),
+        |(total_probability, total_score), (probability, score)| {
+            (
+                total_probability + probability,
+                total_score + probability * score,
+            )
+        },
+    );
+    total_score / total_probability
 }
 
 fn check_out_of_bounds(game_state: GameState) -> GameState {
@@ -81,18 +689,32 @@ fn check_win_lose(game_state: &GameState) -> GameStatus {
     }
 }
 
-fn heuristic_score(game_state: &GameState) -> f32 {
+fn heuristic_score(game_state: &GameState, config: &PlannerConfig) -> f32 {
+    let weights = &config.weights;
     if let Some(player_head) = game_state.player.head() {
-        if let Some(distance_to_food) = game_state
-            .food
-            .iter()
-            .map(|Cell(x, y)| (player_head.0 - x).abs() + (player_head.1 - y).abs())
-            .min()
-        {
-            (10.0 - (distance_to_food as f32 / 10.0)).max(0.0)
+        let distance = |Cell(x, y): Cell| (player_head.0 - x).abs() + (player_head.1 - y).abs();
+        let food_score =
+            if let Some(distance_to_food) = game_state.food.iter().copied().map(distance).min() {
+                (weights.food - distance_to_food as f32 * weights.distance_penalty).max(0.0)
+            } else {
+                weights.no_food
+            };
+        let hunt_score = if config.aggressive {
+            game_state
+                .enemies
+                .iter()
+                .filter(|enemy| enemy.length() < game_state.player.length())
+                .filter_map(|enemy| enemy.head())
+                .map(distance)
+                .min()
+                .map_or(0.0, |distance_to_prey| {
+                    (weights.hunt_bonus - distance_to_prey as f32 * weights.distance_penalty)
+                        .max(0.0)
+                })
         } else {
-            0.1
-        }
+            0.0
+        };
+        food_score + hunt_score
     } else {
         0.0
     }
@@ -166,18 +788,67 @@ fn get_possible_next_states(
     game_state: &GameState,
     player_move: Move,
 ) -> impl ExactSizeIterator<Item = GameState> {
-    MovePermutations::new(game_state.enemies.len().max(1)).map(move |enemy_moves| GameState {
-        height: game_state.height,
-        width: game_state.width,
-        player: game_state.player.update(player_move, &game_state.food),
-        enemies: game_state
+    let num_living_enemies = game_state.enemies.iter().filter(|s| s.is_alive()).count();
+    MovePermutations::new(num_living_enemies.max(1)).map(move |enemy_moves| {
+        let mut enemy_moves = enemy_moves.into_iter();
+        let player = game_state.player.update(player_move, &game_state.food);
+        let enemies: Vec<_> = game_state
             .enemies
+            .iter()
+            .map(|s| {
+                if s.is_alive() {
+                    s.update(enemy_moves.next().unwrap(), &game_state.food)
+                } else {
+                    Battlesnake::new_dead()
+                }
+            })
+            .collect();
+        let food = game_state
+            .food
+            .iter()
+            .copied()
+            .filter(|&f| {
+                std::iter::once(&player)
+                    .chain(&enemies)
+                    .all(|s| s.head() != Some(f))
+            })
+            .collect();
+        GameState {
+            height: game_state.height,
+            width: game_state.width,
+            player,
+            enemies,
+            food,
+            hazards: game_state.hazards.clone(),
+        }
+    })
+}
+
+/// The probability of each outcome of `get_possible_next_states`, in the
+/// same order.
+fn get_outcome_probabilities(
+    game_state: &GameState,
+    opponents: &[Behaviour],
+) -> impl ExactSizeIterator<Item = f32> {
+    let probabilities: Vec<_> = game_state
+        .enemies
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.is_alive())
+        .map(|(i, s)| {
+            opponents
+                .get(i)
+                .copied()
+                .unwrap_or_default()
+                .move_probabilities(game_state, s)
+        })
+        .collect();
+    MovePermutations::new(probabilities.len().max(1)).map(move |enemy_moves| {
+        probabilities
             .iter()
             .zip(enemy_moves)
-            .map(|(s, m)| s.update(m, &game_state.food))
-            .filter(|s| s.is_alive())
-            .collect(),
-        food: game_state.food.clone(),
+            .map(|(p, m)| p[move_index(m)])
+            .product()
     })
 }
 
@@ -196,6 +867,7 @@ mod tests {
                 Battlesnake::new(&[(1, 3), (2, 3), (3, 3), (4, 3), (5, 3)]),
             ],
             food: vec![],
+            hazards: vec![],
         };
         let new_gamestate = check_out_of_bounds(gamestate);
         assert!(new_gamestate.player.is_alive());
@@ -222,7 +894,7 @@ mod tests {
     fn move_permutations_size_hint() {
         for size in 0..16 {
             let target = MovePermutations::new(size);
-            let v = (4 as usize).pow(size as u32);
+            let v = 4_usize.pow(size as u32);
             assert_eq!((v, Some(v)), target.size_hint());
         }
     }
@@ -231,7 +903,7 @@ mod tests {
     fn move_permutations_size_len() {
         for size in 0..16 {
             let mut target = MovePermutations::new(size);
-            let v = (4 as usize).pow(size as u32);
+            let v = 4_usize.pow(size as u32);
             assert_eq!(v, target.len());
             target.next();
             if size > 0 {
@@ -250,7 +922,49 @@ mod tests {
 
     #[test]
     fn combine_scores_yields_average() {
-        assert_eq!(combine_scores([0.0, 3.5, -3.5, 10.0].iter().copied()), 2.5);
+        assert_eq!(
+            combine_scores([0.0, 3.5, -3.5, 10.0].iter().map(|&score| (1.0, score))),
+            2.5
+        );
+    }
+
+    #[test]
+    fn combine_scores_weights_by_probability() {
+        assert_eq!(combine_scores([(0.75, 4.0), (0.25, -4.0)].into_iter()), 2.0);
+        assert_eq!(combine_scores([(3.0, 4.0), (1.0, -4.0)].into_iter()), 2.0);
+    }
+
+    #[test]
+    fn outcome_probabilities_match_possible_next_states() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![
+                Battlesnake::new(&[(7, 2), (7, 3), (8, 3)]),
+                Battlesnake::new_dead(),
+                Battlesnake::new(&[(2, 1), (2, 2), (2, 3), (2, 4)]),
+            ],
+            food: vec![Cell(7, 0)],
+            hazards: vec![],
+        };
+        let opponents = [Behaviour {
+            food_seeking: 3.0,
+            ..Behaviour::default()
+        }];
+        let probabilities: Vec<_> = get_outcome_probabilities(&game_state, &opponents).collect();
+        let states: Vec<_> = get_possible_next_states(&game_state, Move::Up).collect();
+        assert_eq!(probabilities.len(), 16);
+        assert_eq!(states.len(), 16);
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        let most_likely = probabilities.iter().copied().fold(0.0, f32::max);
+        for (&probability, state) in probabilities.iter().zip(&states) {
+            assert_eq!(state.enemies.len(), 3);
+            assert!(!state.enemies[1].is_alive());
+            if probability == most_likely {
+                assert_eq!(state.enemies[0].head(), Some(Cell(7, 1)));
+            }
+        }
     }
 
     #[test]
@@ -261,6 +975,7 @@ mod tests {
             player: Battlesnake::new(&[(1, 2), (1, 3), (1, 4)]),
             enemies: vec![Battlesnake::new_dead(), Battlesnake::new_dead()],
             food: vec![],
+            hazards: vec![],
         };
         assert_eq!(check_win_lose(&game_state), GameStatus::Win);
     }
@@ -276,6 +991,7 @@ mod tests {
                 Battlesnake::new(&[(3, 5), (3, 6), (4, 6)]),
             ],
             food: vec![],
+            hazards: vec![],
         };
         assert_eq!(check_win_lose(&game_state), GameStatus::Lose);
 
@@ -297,6 +1013,7 @@ mod tests {
                 Battlesnake::new(&[(3, 5), (3, 6), (4, 6)]),
             ],
             food: vec![],
+            hazards: vec![],
         };
         assert_eq!(check_win_lose(&game_state), GameStatus::Continue);
 
@@ -318,6 +1035,7 @@ mod tests {
                 Battlesnake::new(&[(2, 1), (2, 2), (2, 3), (2, 4)]),
             ],
             food: vec![],
+            hazards: vec![],
         };
         let results: Vec<_> = get_possible_next_states(&game_state, Move::Left).collect();
         assert_eq!(results.len(), 16);
@@ -341,7 +1059,343 @@ mod tests {
                 Battlesnake::new(&[(2, 1), (2, 2), (2, 3), (2, 4)]),
             ],
             food: vec![],
+            hazards: vec![],
+        };
+        assert_ne!(
+            find_plan(&game_state, &PlannerConfig::default(), &[]).0,
+            Move::Left
+        );
+    }
+
+    #[test]
+    fn find_plan_avoids_head_to_head_with_longer_snake() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(5, 5), (4, 5), (3, 5)]),
+            enemies: vec![Battlesnake::new(&[(7, 5), (8, 5), (9, 5), (10, 5)])],
+            food: vec![],
+            hazards: vec![],
+        };
+        assert_ne!(
+            find_plan(&game_state, &PlannerConfig::default(), &[]).0,
+            Move::Right
+        );
+    }
+
+    #[test]
+    fn find_plan_hunts_shorter_snake_when_aggressive() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(5, 5), (5, 4), (5, 3), (5, 2)]),
+            enemies: vec![Battlesnake::new(&[(7, 5), (8, 5)])],
+            food: vec![],
+            hazards: vec![],
+        };
+        let config = PlannerConfig {
+            aggressive: true,
+            ..PlannerConfig::default()
+        };
+        assert_eq!(find_plan(&game_state, &config, &[]).0, Move::Right);
+    }
+
+    #[test]
+    fn get_possible_next_states_removes_eaten_food() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3)]),
+            enemies: vec![Battlesnake::new(&[(7, 2), (7, 3), (8, 3)])],
+            food: vec![Cell(2, 3), Cell(7, 1), Cell(9, 9)],
+            hazards: vec![],
+        };
+        let results: Vec<_> = get_possible_next_states(&game_state, Move::Left).collect();
+        assert!(results.iter().all(|e| e.player.length() == 4));
+        assert!(results.iter().all(|e| !e.food.contains(&Cell(2, 3))));
+        assert!(results.iter().all(|e| e.food.contains(&Cell(9, 9))));
+        assert_eq!(
+            results
+                .iter()
+                .filter(|e| !e.food.contains(&Cell(7, 1)))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn search_tree_advances_to_realized_outcome() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![
+                Battlesnake::new(&[(7, 2), (7, 3), (8, 3)]),
+                Battlesnake::new(&[(0, 5), (0, 6), (0, 7), (0, 8)]),
+            ],
+            food: vec![Cell(9, 9)],
+            hazards: vec![],
+        };
+        let config = PlannerConfig::default();
+        let mut tree = SearchTree::new(game_state.clone());
+        tree.find_plan(&config, &[], None);
+
+        // The player goes up, the first enemy down and the second off the board.
+        let observed = GameState {
+            player: game_state.player.update(Move::Up, &game_state.food),
+            enemies: vec![game_state.enemies[0].update(Move::Down, &game_state.food)],
+            ..game_state.clone()
+        };
+        let opponents = [Behaviour {
+            food_seeking: 2.0,
+            ..Behaviour::default()
+        }];
+        let mut tree = tree.advance(&observed).unwrap();
+        assert_eq!(tree.root.game_state.enemies.len(), 2);
+        assert!(tree.root.branches.is_some());
+        let plan = tree.find_plan(&config, &opponents, None);
+        assert_eq!(
+            (plan.player_move, plan.score),
+            find_plan(&observed, &config, &opponents)
+        );
+    }
+
+    #[test]
+    fn search_tree_is_trimmed_to_its_deepest_levels_that_fit() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![Battlesnake::new(&[(7, 2), (7, 3), (8, 3)])],
+            food: vec![Cell(9, 9)],
+            hazards: vec![],
+        };
+        let config = PlannerConfig {
+            search_depth: 2,
+            ..PlannerConfig::default()
+        };
+        let mut tree = SearchTree::new(game_state);
+        tree.find_plan(&config, &[], None);
+        let level_sizes = |tree: &SearchTree| {
+            let mut level_sizes = Vec::new();
+            tree.root.count_levels(0, &mut level_sizes);
+            level_sizes
+        };
+        let full = level_sizes(&tree);
+        assert_eq!(full.len(), 3);
+
+        tree.trim(full.iter().sum());
+        assert_eq!(level_sizes(&tree), full);
+        tree.trim(full[0] + full[1]);
+        assert_eq!(level_sizes(&tree), full[..2]);
+        tree.trim(full[0]);
+        assert_eq!(level_sizes(&tree), [1]);
+        assert!(tree.root.branches.is_none());
+    }
+
+    #[test]
+    fn search_tree_does_not_advance_when_food_spawns() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![Battlesnake::new(&[(7, 2), (7, 3), (8, 3)])],
+            food: vec![],
+            hazards: vec![],
+        };
+        let mut tree = SearchTree::new(game_state.clone());
+        tree.find_plan(&PlannerConfig::default(), &[], None);
+        let observed = GameState {
+            player: game_state.player.update(Move::Up, &[]),
+            enemies: vec![game_state.enemies[0].update(Move::Down, &[])],
+            food: vec![Cell(1, 1)],
+            ..game_state
+        };
+        assert!(tree.advance(&observed).is_none());
+    }
+
+    #[test]
+    fn search_tree_does_not_advance_when_hazards_change() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![Battlesnake::new(&[(7, 2), (7, 3), (8, 3)])],
+            food: vec![],
+            hazards: vec![Cell(0, 0)],
+        };
+        let searched_tree = || {
+            let mut tree = SearchTree::new(game_state.clone());
+            tree.find_plan(&PlannerConfig::default(), &[], None);
+            tree
+        };
+        let observed = GameState {
+            player: game_state.player.update(Move::Up, &[]),
+            enemies: vec![game_state.enemies[0].update(Move::Down, &[])],
+            ..game_state.clone()
+        };
+        assert!(searched_tree().advance(&observed).is_some());
+        let shrunk = GameState {
+            hazards: vec![Cell(0, 0), Cell(0, 1)],
+            ..observed
+        };
+        assert!(searched_tree().advance(&shrunk).is_none());
+    }
+
+    #[test]
+    fn paranoid_strategy_assumes_the_worst() {
+        // Every move risks a head-on collision with a longer snake, which
+        // paranoia treats as certain.
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(5, 5), (5, 4), (5, 3)]),
+            enemies: vec![
+                Battlesnake::new(&[(4, 6), (3, 6), (2, 6), (1, 6), (0, 6)]),
+                Battlesnake::new(&[(6, 6), (7, 6), (8, 6), (9, 6), (10, 6)]),
+            ],
+            food: vec![],
+            hazards: vec![],
+        };
+        let config = PlannerConfig {
+            search_depth: 1,
+            ..PlannerConfig::default()
+        };
+        let (_, expectimax_score) = find_plan(&game_state, &config, &[]);
+        let config = PlannerConfig {
+            strategy: Strategy::Paranoid,
+            ..config
+        };
+        let (_, paranoid_score) = find_plan(&game_state, &config, &[]);
+        assert_eq!(paranoid_score, LOSE_VALUE - HEAD_TO_HEAD_PENALTY);
+        assert!(expectimax_score > paranoid_score);
+    }
+
+    #[test]
+    fn find_plan_returns_a_shallow_plan_when_out_of_time() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![
+                Battlesnake::new(&[(7, 2), (7, 3), (8, 3)]),
+                Battlesnake::new(&[(2, 1), (2, 2), (2, 3), (2, 4)]),
+            ],
+            food: vec![],
+            hazards: vec![],
+        };
+        let config = PlannerConfig {
+            search_depth: 8,
+            ..PlannerConfig::default()
+        };
+        let mut tree = SearchTree::new(game_state);
+        let plan = tree.find_plan(&config, &[], Some(Instant::now()));
+        assert_ne!(plan.player_move, Move::Left);
+        assert_eq!(plan.search_depth, 1);
+        assert!(plan.nodes_searched > 1);
+    }
+
+    #[test]
+    fn devise_plan_gives_up_at_the_hard_deadline() {
+        use std::time::Duration;
+
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![Battlesnake::new(&[(7, 2), (7, 3), (8, 3)])],
+            food: vec![],
+            hazards: vec![],
+        };
+        let config = PlannerConfig {
+            search_depth: 8,
+            ..PlannerConfig::default()
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let now = Instant::now();
+        let (_, plan) = runtime
+            .block_on(devise_plan(
+                SearchTree::new(game_state.clone()),
+                config,
+                vec![],
+                now,
+                now + Duration::from_secs(10),
+                SearchTree::find_cancellable_plan,
+            ))
+            .unwrap();
+        assert_eq!(plan.search_depth, 1);
+        let now = Instant::now();
+        let result = runtime.block_on(devise_plan(
+            SearchTree::new(game_state),
+            config,
+            vec![],
+            now + Duration::from_millis(50),
+            now,
+            SearchTree::find_cancellable_plan,
+        ));
+        assert!(matches!(result, Err(Error::PlannerTimeout { .. })));
+    }
+
+    #[test]
+    fn cancelled_searches_stop_in_the_first_ply() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(3, 3), (4, 3), (5, 3), (6, 3)]),
+            enemies: vec![Battlesnake::new(&[(7, 2), (7, 3), (8, 3)])],
+            food: vec![],
+            hazards: vec![],
+        };
+        let config = PlannerConfig::default();
+        let mut tree = SearchTree::new(game_state);
+        let cancelled = AtomicBool::new(true);
+        assert_eq!(
+            tree.find_cancellable_plan(&config, &[], None, &cancelled),
+            None
+        );
+        cancelled.store(false, Ordering::Relaxed);
+        assert!(
+            tree.find_cancellable_plan(&config, &[], None, &cancelled)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn fallback_move_avoids_walls_bodies_and_longer_heads() {
+        let game_state = GameState {
+            height: 11,
+            width: 11,
+            player: Battlesnake::new(&[(0, 5), (1, 5), (2, 5)]),
+            enemies: vec![Battlesnake::new(&[(0, 7), (0, 8), (0, 9), (0, 10)])],
+            food: vec![],
+            hazards: vec![],
+        };
+        assert_eq!(fallback_move(&game_state), Move::Down);
+        let cornered = GameState {
+            player: Battlesnake::new(&[(0, 0), (1, 0), (1, 1), (0, 1), (0, 1)]),
+            enemies: vec![],
+            ..game_state
+        };
+        assert_eq!(fallback_move(&cornered), Move::Up);
+    }
+
+    #[test]
+    fn find_plan_handles_the_corners_of_the_largest_board() {
+        let edge = crate::game_state::MAX_BOARD_SIZE as usize - 1;
+        let game_state = GameState {
+            height: edge as u16 + 1,
+            width: edge as u16 + 1,
+            player: Battlesnake::new(&[(edge, edge), (edge - 1, edge), (edge - 2, edge)]),
+            enemies: vec![Battlesnake::new(&[
+                (edge, 0),
+                (edge, 1),
+                (edge, 2),
+                (edge, 3),
+            ])],
+            food: vec![Cell::at(0, edge)],
+            hazards: vec![],
         };
-        assert_ne!(find_plan(&game_state, 2).0, Move::Left);
+        let (player_move, _) = find_plan(&game_state, &PlannerConfig::default(), &[]);
+        assert_eq!(player_move, Move::Down);
     }
 }
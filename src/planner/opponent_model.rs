@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+
+use crate::{
+    api_types,
+    game_state::{Battlesnake, Cell, GameState, Move},
+};
+
+/// Learns how each enemy in a single game tends to move, from the boards we
+/// are sent on successive turns.
+#[derive(Debug, Default)]
+pub struct OpponentModel {
+    snakes: HashMap<String, SnakeHistory>,
+}
+
+impl OpponentModel {
+    /// Records the moves every snake made since the previous board we saw.
+    pub fn observe(&mut self, board: &api_types::Board) {
+        for snake in &board.snakes {
+            let Ok(game_state) = GameState::from_board(board, &snake.id) else {
+                continue;
+            };
+            let history = self.snakes.entry(snake.id.clone()).or_default();
+            if let (Some(previous), Some(head)) =
+                (history.previous.take(), game_state.player.head())
+                && let Some(observed_move) = move_between(previous.head, head)
+                && let Some(chosen) = previous.features[move_index(observed_move)]
+            {
+                let options: Vec<_> = previous.features.iter().flatten().collect();
+                let legal = options.len();
+                let available =
+                    |f: fn(&MoveFeatures) -> bool| options.iter().filter(|o| f(o)).count();
+                history
+                    .food_seeking
+                    .record(available(|o| o.food), legal, chosen.food);
+                history
+                    .aggression
+                    .record(available(|o| o.aggression), legal, chosen.aggression);
+                history
+                    .wall_hugging
+                    .record(available(|o| o.wall), legal, chosen.wall);
+            }
+            let legal_moves = game_state.legal_moves(&game_state.player);
+            history.previous = game_state.player.head().map(|head| Observation {
+                head,
+                features: [Move::Up, Move::Down, Move::Left, Move::Right].map(|m| {
+                    legal_moves
+                        .contains(&m)
+                        .then(|| MoveFeatures::new(&game_state, &game_state.player, m))
+                }),
+            });
+        }
+    }
+
+    /// The learned behaviour of every snake on `board` other than the
+    /// player, in the same order as `GameState::from_board` lists enemies.
+    pub fn behaviours(&self, board: &api_types::Board, player_id: &str) -> Vec<Behaviour> {
+        board
+            .snakes
+            .iter()
+            .filter(|snake| snake.id != player_id)
+            .map(|snake| {
+                self.snakes
+                    .get(&snake.id)
+                    .map(SnakeHistory::behaviour)
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+/// How strongly a snake favours moves with each feature, as the odds of it
+/// picking such a move relative to a snake moving at random. A weight of 1.0
+/// means no preference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Behaviour {
+    pub food_seeking: f32,
+    pub aggression: f32,
+    pub wall_hugging: f32,
+}
+
+impl Default for Behaviour {
+    fn default() -> Self {
+        Self {
+            food_seeking: 1.0,
+            aggression: 1.0,
+            wall_hugging: 1.0,
+        }
+    }
+}
+
+impl Behaviour {
+    /// The probability of `snake` making each move, indexed in the order of
+    /// `Move::enumerate`. Moves that kill the snake outright are never made
+    /// unless every move does.
+    pub fn move_probabilities(&self, game_state: &GameState, snake: &Battlesnake) -> [f32; 4] {
+        let legal_moves = game_state.legal_moves(snake);
+        if legal_moves.is_empty() {
+            return [0.25; 4];
+        }
+        let mut weights = [0.0; 4];
+        for (weight, snake_move) in weights.iter_mut().zip(Move::enumerate()) {
+            if !legal_moves.contains(&snake_move) {
+                continue;
+            }
+            *weight = 1.0;
+            let features = MoveFeatures::new(game_state, snake, snake_move);
+            if features.food {
+                *weight *= self.food_seeking;
+            }
+            if features.aggression {
+                *weight *= self.aggression;
+            }
+            if features.wall {
+                *weight *= self.wall_hugging;
+            }
+        }
+        let total: f32 = weights.iter().sum();
+        weights.map(|weight| weight / total)
+    }
+}
+
+/// The position of `snake_move` in the order of `Move::enumerate`.
+pub fn move_index(snake_move: Move) -> usize {
+    match snake_move {
+        Move::Up => 0,
+        Move::Down => 1,
+        Move::Left => 2,
+        Move::Right => 3,
+    }
+}
+
+fn move_between(from: Cell, to: Cell) -> Option<Move> {
+    Move::enumerate().find(|&m| from + m == to)
+}
+
+#[derive(Debug, Default)]
+struct SnakeHistory {
+    previous: Option<Observation>,
+    food_seeking: Tendency,
+    aggression: Tendency,
+    wall_hugging: Tendency,
+}
+
+impl SnakeHistory {
+    fn behaviour(&self) -> Behaviour {
+        Behaviour {
+            food_seeking: self.food_seeking.weight(),
+            aggression: self.aggression.weight(),
+            wall_hugging: self.wall_hugging.weight(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Observation {
+    head: Cell,
+    /// The features of each move in the order of `Move::enumerate`, or
+    /// `None` for moves the snake could not make without dying.
+    features: [Option<MoveFeatures>; 4],
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct MoveFeatures {
+    /// Brings the head closer to the nearest food.
+    food: bool,
+    /// Brings the head closer to the nearest other snake's head.
+    aggression: bool,
+    /// Ends on the edge of the board.
+    wall: bool,
+}
+
+impl MoveFeatures {
+    fn new(game_state: &GameState, snake: &Battlesnake, snake_move: Move) -> Self {
+        let Some(head) = snake.head() else {
+            return Self::default();
+        };
+        let new_head = head + snake_move;
+        let distance =
+            |a: Cell, b: Cell| (a.0 as i32 - b.0 as i32).abs() + (a.1 as i32 - b.1 as i32).abs();
+        let nearest = |targets: &mut dyn Iterator<Item = Cell>| {
+            targets.min_by_key(|&target| distance(head, target))
+        };
+        let food = nearest(&mut game_state.food.iter().copied())
+            .is_some_and(|food| distance(new_head, food) < distance(head, food));
+        let aggression = nearest(
+            &mut game_state
+                .snakes()
+                .filter_map(Battlesnake::head)
+                .filter(|&other| other != head),
+        )
+        .is_some_and(|other| distance(new_head, other) < distance(head, other));
+        let Cell(x, y) = new_head;
+        let wall = x == 0
+            || y == 0
+            || x as i32 == game_state.width as i32 - 1
+            || y as i32 == game_state.height as i32 - 1;
+        Self {
+            food,
+            aggression,
+            wall,
+        }
+    }
+}
+
+/// Counts how often a snake picked a move with some feature, against how
+/// often it would have done so by moving at random among its legal moves.
+#[derive(Debug, Default, Clone, Copy)]
+struct Tendency {
+    hits: f32,
+    misses: f32,
+    expected_hits: f32,
+    expected_misses: f32,
+}
+
+/// Pseudo-observations pulling the weight towards no preference until there
+/// is enough evidence.
+static TENDENCY_PRIOR: f32 = 2.0;
+
+impl Tendency {
+    /// Records one decision where `available` of the `legal` moves had the
+    /// feature. Decisions where every move or no move had it tell us nothing.
+    fn record(&mut self, available: usize, legal: usize, chosen: bool) {
+        if available == 0 || available == legal {
+            return;
+        }
+        let chance = available as f32 / legal as f32;
+        self.expected_hits += chance;
+        self.expected_misses += 1.0 - chance;
+        if chosen {
+            self.hits += 1.0;
+        } else {
+            self.misses += 1.0;
+        }
+    }
+
+    fn weight(&self) -> f32 {
+        ((self.hits + TENDENCY_PRIOR) / (self.expected_hits + TENDENCY_PRIOR))
+            / ((self.misses + TENDENCY_PRIOR) / (self.expected_misses + TENDENCY_PRIOR))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_snake(id: &str, body: &[(i32, i32)]) -> api_types::Battlesnake {
+        let body: Vec<_> = body
+            .iter()
+            .map(|&(x, y)| api_types::Coordinates { x, y })
+            .collect();
+        api_types::Battlesnake {
+            id: id.into(),
+            name: id.into(),
+            health: 100,
+            head: api_types::Coordinates {
+                x: body[0].x,
+                y: body[0].y,
+            },
+            length: body.len() as u16,
+            body,
+            latency: "0".into(),
+            shout: "".into(),
+            squad: "".into(),
+            customizations: serde_json::Value::Null,
+        }
+    }
+
+    fn api_board(snakes: Vec<api_types::Battlesnake>, food: &[(i32, i32)]) -> api_types::Board {
+        api_types::Board {
+            height: 11,
+            width: 11,
+            food: food
+                .iter()
+                .map(|&(x, y)| api_types::Coordinates { x, y })
+                .collect(),
+            hazards: vec![],
+            snakes,
+        }
+    }
+
+    #[test]
+    fn unknown_snakes_move_uniformly_among_legal_moves() {
+        let board = api_board(
+            vec![
+                api_snake("you", &[(1, 1), (1, 2), (1, 3)]),
+                api_snake("them", &[(5, 5), (5, 6), (5, 7)]),
+            ],
+            &[(5, 0)],
+        );
+        let model = OpponentModel::default();
+        let behaviours = model.behaviours(&board, "you");
+        assert_eq!(behaviours, vec![Behaviour::default()]);
+
+        let game_state = GameState::from_board(&board, "you").unwrap();
+        let third = 1.0 / 3.0;
+        assert_eq!(
+            behaviours[0].move_probabilities(&game_state, &game_state.enemies[0]),
+            [0.0, third, third, third]
+        );
+    }
+
+    #[test]
+    fn trapped_snakes_move_uniformly() {
+        let board = api_board(
+            vec![
+                api_snake("you", &[(5, 5), (5, 6), (5, 7)]),
+                api_snake("them", &[(0, 0), (0, 1), (1, 1), (1, 0), (2, 0)]),
+            ],
+            &[],
+        );
+        let game_state = GameState::from_board(&board, "you").unwrap();
+        assert!(game_state.legal_moves(&game_state.enemies[0]).is_empty());
+        assert_eq!(
+            Behaviour::default().move_probabilities(&game_state, &game_state.enemies[0]),
+            [0.25; 4]
+        );
+    }
+
+    #[test]
+    fn learns_food_seeking() {
+        let mut model = OpponentModel::default();
+        let you = [(1, 10), (1, 9), (1, 8)];
+        for turn in 0..4 {
+            model.observe(&api_board(
+                vec![
+                    api_snake("you", &you),
+                    api_snake("them", &[(8, 8 - turn), (8, 9 - turn), (8, 10 - turn)]),
+                ],
+                &[(8, 0)],
+            ));
+        }
+        let board = api_board(
+            vec![
+                api_snake("you", &you),
+                api_snake("them", &[(8, 4), (8, 5), (8, 6)]),
+            ],
+            &[(8, 0)],
+        );
+        let behaviours = model.behaviours(&board, "you");
+        assert!(behaviours[0].food_seeking > 1.0);
+        assert!(behaviours[0].aggression < 1.0);
+        assert_eq!(behaviours[0].wall_hugging, 1.0);
+
+        let game_state = GameState::from_board(&board, "you").unwrap();
+        let probabilities = behaviours[0].move_probabilities(&game_state, &game_state.enemies[0]);
+        assert!(probabilities[move_index(Move::Down)] > 0.25);
+        assert!((probabilities.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ignores_snakes_that_did_not_move_one_cell() {
+        let mut model = OpponentModel::default();
+        model.observe(&api_board(
+            vec![api_snake("them", &[(8, 9), (8, 10), (8, 11)])],
+            &[(8, 0)],
+        ));
+        model.observe(&api_board(
+            vec![api_snake("them", &[(2, 2), (2, 3), (2, 4)])],
+            &[(8, 0)],
+        ));
+        assert_eq!(model.snakes["them"].food_seeking.expected_hits, 0.0);
+    }
+
+    #[test]
+    fn learns_only_from_moves_the_snake_could_make() {
+        let mut model = OpponentModel::default();
+        // Only turning back into the neck would bring `them` closer to the
+        // food, so picking another move says nothing about food seeking.
+        for turn in 0..4 {
+            model.observe(&api_board(
+                vec![api_snake(
+                    "them",
+                    &[(5, 5 - turn), (5, 6 - turn), (5, 7 - turn)],
+                )],
+                &[(5, 10)],
+            ));
+        }
+        assert_eq!(model.snakes["them"].food_seeking.expected_hits, 0.0);
+        assert_eq!(model.snakes["them"].food_seeking.weight(), 1.0);
+    }
+}
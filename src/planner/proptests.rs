@@ -0,0 +1,165 @@
+//! Invariants of the simulation, checked on generated boards.
+
+use proptest::{collection::vec, prelude::*};
+
+use super::{check_collisions, check_out_of_bounds, get_possible_next_states};
+use crate::game_state::{Battlesnake, Cell, Coord, GameState, Move};
+
+fn moves() -> impl Strategy<Value = Move> {
+    prop_oneof![
+        Just(Move::Up),
+        Just(Move::Down),
+        Just(Move::Left),
+        Just(Move::Right),
+    ]
+}
+
+/// Boards of up to four snakes that could come up in a game: every snake is
+/// a connected path of cells, possibly with a stacked tail, no two snakes
+/// overlap, and food is only on free cells. The first snake is the player.
+fn game_states() -> impl Strategy<Value = GameState> {
+    (3 as Coord..=11, 3 as Coord..=11).prop_flat_map(|(width, height)| {
+        let cell = (0..width, 0..height).prop_map(|(x, y)| Cell(x, y));
+        let snake = (cell.clone(), vec(moves(), 0..10), any::<bool>());
+        (vec(snake, 1..=4), vec(cell, 0..4))
+            .prop_map(move |(snakes, food)| build(width, height, snakes, food))
+    })
+}
+
+/// Lays each snake from its head along `path`, skipping steps that would
+/// leave the board or cross a snake. Snakes whose head is taken are left out.
+fn build(
+    width: Coord,
+    height: Coord,
+    snakes: Vec<(Cell, Vec<Move>, bool)>,
+    food: Vec<Cell>,
+) -> GameState {
+    let on_board = |Cell(x, y): Cell| x >= 0 && y >= 0 && x < width && y < height;
+    let mut occupied: Vec<Cell> = vec![];
+    let mut laid = vec![];
+    for (head, path, stacked) in snakes {
+        if occupied.contains(&head) {
+            continue;
+        }
+        let mut cells = vec![head];
+        for step in path {
+            let next = *cells.last().unwrap() + step;
+            if on_board(next) && !occupied.contains(&next) && !cells.contains(&next) {
+                cells.push(next);
+            }
+        }
+        if stacked {
+            cells.push(*cells.last().unwrap());
+        }
+        occupied.extend(&cells);
+        let cells: Vec<_> = cells
+            .iter()
+            .map(|&Cell(x, y)| (x as usize, y as usize))
+            .collect();
+        laid.push(Battlesnake::new(&cells));
+    }
+    let mut snakes = laid.into_iter();
+    let mut free_food = vec![];
+    for cell in food {
+        if !occupied.contains(&cell) && !free_food.contains(&cell) {
+            free_food.push(cell);
+        }
+    }
+    GameState {
+        height: height as u16,
+        width: width as u16,
+        player: snakes.next().unwrap(),
+        enemies: snakes.collect(),
+        food: free_food,
+        hazards: vec![],
+    }
+}
+
+/// Moves every snake, with the player's move first, as the planner does
+/// before checking for deaths.
+fn apply_moves(game_state: &GameState, moves: &[Move]) -> GameState {
+    let mut moves = moves.iter().copied().cycle();
+    let mut update = |snake: &Battlesnake| snake.update(moves.next().unwrap(), &game_state.food);
+    GameState {
+        player: update(&game_state.player),
+        enemies: game_state.enemies.iter().map(&mut update).collect(),
+        ..game_state.clone()
+    }
+}
+
+fn resolve(game_state: GameState) -> GameState {
+    check_collisions(check_out_of_bounds(game_state))
+}
+
+proptest! {
+    #[test]
+    fn lengths_change_by_at_most_one(game_state in game_states(), player_move in moves()) {
+        for next in get_possible_next_states(&game_state, player_move) {
+            for (before, after) in game_state.snakes().zip(next.snakes()) {
+                if after.is_alive() {
+                    prop_assert!(
+                        after.length() == before.length() || after.length() == before.length() + 1
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn heads_are_on_the_board_after_check_out_of_bounds(
+        game_state in game_states(),
+        moves in vec(moves(), 4),
+    ) {
+        let checked = check_out_of_bounds(apply_moves(&game_state, &moves));
+        for head in checked.snakes().filter_map(Battlesnake::head) {
+            let Cell(x, y) = head;
+            prop_assert!(x >= 0 && y >= 0);
+            prop_assert!((x as u16) < checked.width && (y as u16) < checked.height);
+        }
+    }
+
+    #[test]
+    fn live_snakes_never_share_a_head(game_state in game_states(), moves in vec(moves(), 4)) {
+        let resolved = resolve(apply_moves(&game_state, &moves));
+        let heads: Vec<_> = resolved.snakes().filter_map(Battlesnake::head).collect();
+        for (i, head) in heads.iter().enumerate() {
+            prop_assert!(!heads[i + 1..].contains(head), "{:?} share a head", heads);
+        }
+    }
+
+    #[test]
+    fn dead_snakes_are_empty(game_state in game_states(), moves in vec(moves(), 4)) {
+        let resolved = resolve(apply_moves(&game_state, &moves));
+        prop_assert_eq!(resolved.enemies.len(), game_state.enemies.len());
+        for snake in resolved.snakes().filter(|s| !s.is_alive()) {
+            prop_assert_eq!(snake.length(), 0);
+            prop_assert_eq!(snake.head(), None);
+            prop_assert!(snake.body().is_empty());
+        }
+    }
+
+    #[test]
+    fn collisions_do_not_depend_on_the_order_of_snakes(
+        (game_state, order) in game_states().prop_flat_map(|game_state| {
+            let order: Vec<usize> = (0..=game_state.enemies.len()).collect();
+            (Just(game_state), Just(order).prop_shuffle())
+        }),
+        moves in vec(moves(), 4),
+    ) {
+        let moved = check_out_of_bounds(apply_moves(&game_state, &moves));
+        let snakes: Vec<_> = moved.snakes().cloned().collect();
+        let reordered = GameState {
+            player: snakes[order[0]].clone(),
+            enemies: order[1..].iter().map(|&i| snakes[i].clone()).collect(),
+            ..moved.clone()
+        };
+        let survived: Vec<_> = check_collisions(moved).snakes().map(|s| s.is_alive()).collect();
+        let reordered_survived: Vec<_> = check_collisions(reordered)
+            .snakes()
+            .map(|s| s.is_alive())
+            .collect();
+        for (position, &i) in order.iter().enumerate() {
+            prop_assert_eq!(reordered_survived[position], survived[i], "snake {}", i);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use clap::Args;
+
+use blaze_haskell::{
+    config::Config,
+    engine::{self, HttpPlayer, MatchSettings, PlannerPlayer, Player},
+    error::{Error, Result},
+    planner::PlannerConfig,
+};
+
+/// Plays local games between snakes and reports who won.
+#[derive(Args)]
+pub struct PlayArgs {
+    /// A snake to play: "local" for our planner as configured, "local:PREFIX"
+    /// for one of the configured personalities, or the http:// URL of a snake
+    /// server. Give once per snake.
+    #[arg(long = "snake", required = true)]
+    snakes: Vec<String>,
+    /// How many games to play
+    #[arg(long, default_value_t = 1)]
+    games: u32,
+    /// Seed for the first game; each further game adds one
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long, default_value_t = 11)]
+    width: u16,
+    #[arg(long, default_value_t = 11)]
+    height: u16,
+    /// Milliseconds each snake has to answer each move
+    #[arg(long, default_value_t = 500)]
+    timeout_ms: u32,
+    /// Call games still going after this many turns a draw
+    #[arg(long)]
+    max_turns: Option<u32>,
+}
+
+pub async fn run(args: &PlayArgs, config: &Config) -> Result<()> {
+    let mut players = args
+        .snakes
+        .iter()
+        .map(|spec| player(spec, config))
+        .collect::<Result<Vec<_>>>()?;
+    let settings = MatchSettings {
+        width: args.width,
+        height: args.height,
+        timeout_ms: args.timeout_ms,
+        max_turns: args.max_turns,
+    };
+
+    let mut wins = vec![0; players.len()];
+    let mut draws = 0;
+    let mut total_turns = 0;
+    for game in 0..args.games {
+        let seed = args.seed.wrapping_add(u64::from(game));
+        let outcome = engine::play_game(&mut players, &settings, seed).await?;
+        total_turns += outcome.turns;
+        match outcome.winner {
+            Some(winner) => {
+                wins[winner] += 1;
+                println!(
+                    "Game {} (seed {}): {} won after {} turns",
+                    game + 1,
+                    seed,
+                    args.snakes[winner],
+                    outcome.turns
+                );
+            }
+            None => {
+                draws += 1;
+                println!(
+                    "Game {} (seed {}): draw after {} turns",
+                    game + 1,
+                    seed,
+                    outcome.turns
+                );
+            }
+        }
+    }
+
+    println!("Results over {} games:", args.games);
+    for (i, spec) in args.snakes.iter().enumerate() {
+        println!("  {}. {:<30} {} wins", i + 1, spec, wins[i]);
+    }
+    println!("  {:<33} {}", "draws", draws);
+    if args.games > 0 {
+        println!(
+            "Average game length: {:.1} turns",
+            f64::from(total_turns) / f64::from(args.games)
+        );
+    }
+    Ok(())
+}
+
+fn player(spec: &str, config: &Config) -> Result<Box<dyn Player>> {
+    if spec.starts_with("http://") {
+        return Ok(Box::new(HttpPlayer::new(spec, spec)));
+    }
+    match local_planner(spec, config)? {
+        Some(planner) => Ok(Box::new(PlannerPlayer::new(
+            spec,
+            planner,
+            config.timing.latency_margin_ms,
+        ))),
+        None => Err(Error::config(format!(
+            "Unknown snake \"{}\"; expected \"local\", \"local:PREFIX\" or an http:// URL.",
+            spec
+        ))),
+    }
+}
+
+/// The planner settings of a "local" or "local:PREFIX" snake, or `None` if
+/// `spec` is neither.
+pub fn local_planner(spec: &str, config: &Config) -> Result<Option<PlannerConfig>> {
+    if spec == "local" {
+        Ok(Some(config.planner))
+    } else if let Some(prefix) = spec.strip_prefix("local:") {
+        let personality = config
+            .personalities
+            .iter()
+            .find(|p| p.prefix == prefix)
+            .ok_or_else(|| {
+                Error::config(format!("No personality has the prefix \"{}\".", prefix))
+            })?;
+        Ok(Some(personality.planner))
+    } else {
+        Ok(None)
+    }
+}
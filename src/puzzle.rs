@@ -0,0 +1,112 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::path::{Path, PathBuf},
+};
+
+use crate::{
+    api_types::MoveRequest,
+    error::{Error, Result},
+    game_state::{GameState, Move},
+    planner::PlannerConfig,
+};
+
+/// A board with the moves the planner should or should not make on it, kept
+/// as a TOML file:
+///
+/// ```toml
+/// description = "Don't follow the food into the pocket"
+/// expected = ["up"]
+/// forbidden = ["right"]
+/// board = """
+/// .......
+/// ...Yy*.
+/// """
+/// ```
+///
+/// The board is either drawn, as by `GameState`'s `Display`, or a JSON
+/// `/move` request in the file named by `request`, relative to the puzzle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Puzzle {
+    pub description: String,
+    /// The planner must pick one of these, if any are given.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expected: Vec<Move>,
+    /// The planner must not pick any of these.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forbidden: Vec<Move>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub planner: PlannerConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub board: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<PathBuf>,
+}
+
+fn is_default(planner: &PlannerConfig) -> bool {
+    *planner == PlannerConfig::default()
+}
+
+impl Puzzle {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::io(format!("Could not read {}", path.display()), e))?;
+        toml::from_str(&contents)
+            .map_err(|e| Error::parse(format!("Could not parse puzzle {}", path.display()), e))
+    }
+
+    /// The puzzle's board. `dir` is where the puzzle file is, for finding
+    /// its `request`.
+    pub fn game_state(&self, dir: &Path) -> Result<GameState> {
+        match (&self.board, &self.request) {
+            (Some(board), None) => board.parse(),
+            (None, Some(request)) => {
+                let path = dir.join(request);
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| Error::io(format!("Could not read {}", path.display()), e))?;
+                let request: MoveRequest = serde_json::from_str(&contents).map_err(|e| {
+                    Error::parse(
+                        format!("Could not parse move request {}", path.display()),
+                        e,
+                    )
+                })?;
+                GameState::from_board(&request.board, &request.you.id)
+            }
+            _ => Err(Error::invalid_text(
+                "A puzzle needs exactly one of a board or a request.",
+            )),
+        }
+    }
+
+    /// Whether `chosen` solves the puzzle.
+    pub fn accepts(&self, chosen: Move) -> bool {
+        (self.expected.is_empty() || self.expected.contains(&chosen))
+            && !self.forbidden.contains(&chosen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puzzles_round_trip_through_toml() {
+        let puzzle = Puzzle {
+            description: "Take the food".into(),
+            expected: vec![Move::Right],
+            forbidden: vec![Move::Left, Move::Up],
+            planner: PlannerConfig::default(),
+            board: Some("....\n.Yy.\n*...\n".into()),
+            request: None,
+        };
+        let written = toml::to_string(&puzzle).unwrap();
+        assert!(!written.contains("planner"));
+        let read: Puzzle = toml::from_str(&written).unwrap();
+        assert_eq!(read, puzzle);
+        let game_state = read.game_state(Path::new(".")).unwrap();
+        assert_eq!(game_state.food.len(), 1);
+        assert!(puzzle.accepts(Move::Right));
+        assert!(!puzzle.accepts(Move::Down));
+        assert!(!puzzle.accepts(Move::Left));
+    }
+}
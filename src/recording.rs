@@ -0,0 +1,316 @@
+use {
+    serde::{Deserialize, Serialize},
+    std::{
+        fs::OpenOptions,
+        io::{BufRead, BufReader, ErrorKind, Write},
+        path::{Path, PathBuf},
+    },
+};
+
+use crate::{
+    api_types::{EndRequest, MoveRequest, MoveResponse, StartRequest},
+    decision_log::game_file_name,
+    error::{Error, Result},
+    game_state::{GameState, Move},
+};
+
+/// The version of the recording format written by `GameRecorder`. Recordings
+/// of any other version are refused by `Recording::load`.
+pub static RECORDING_VERSION: u32 = 1;
+
+/// One line of a recording. A recording is a file of these as JSON lines: a
+/// header, then the requests of the game in the order they arrived.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Entry {
+    Header {
+        version: u32,
+        snake: String,
+    },
+    Start {
+        request: StartRequest,
+    },
+    Move {
+        request: MoveRequest,
+        response: MoveResponse,
+    },
+    End {
+        request: EndRequest,
+    },
+}
+
+/// Records every game a snake plays, one file per game.
+#[derive(Debug, Clone)]
+pub struct GameRecorder {
+    dir: PathBuf,
+}
+
+impl GameRecorder {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The file holding the recording of `game_id` as played by `snake`.
+    pub fn path(&self, game_id: &str, snake: &str) -> PathBuf {
+        self.dir
+            .join(game_file_name(game_id, snake, "recording.jsonl"))
+    }
+
+    pub fn record_start(&self, snake: &str, request: &StartRequest) -> Result<()> {
+        self.append(
+            &request.game.id,
+            snake,
+            &Entry::Start {
+                request: request.clone(),
+            },
+        )
+    }
+
+    pub fn record_move(
+        &self,
+        snake: &str,
+        request: &MoveRequest,
+        response: &MoveResponse,
+    ) -> Result<()> {
+        self.append(
+            &request.game.id,
+            snake,
+            &Entry::Move {
+                request: request.clone(),
+                response: response.clone(),
+            },
+        )
+    }
+
+    pub fn record_end(&self, snake: &str, request: &EndRequest) -> Result<()> {
+        self.append(
+            &request.game.id,
+            snake,
+            &Entry::End {
+                request: request.clone(),
+            },
+        )
+    }
+
+    /// Appends `entry` to the recording, starting it with a header if it is
+    /// new, e.g. because we missed the game's `/start`. The header is written
+    /// along with the first entry by whoever creates the file, so a recording
+    /// never gets two.
+    fn append(&self, game_id: &str, snake: &str, entry: &Entry) -> Result<()> {
+        let path = self.path(game_id, snake);
+        let write = || -> std::io::Result<()> {
+            std::fs::create_dir_all(&self.dir)?;
+            let mut lines = Vec::new();
+            let mut file = match OpenOptions::new().append(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    let header = Entry::Header {
+                        version: RECORDING_VERSION,
+                        snake: snake.into(),
+                    };
+                    serde_json::to_writer(&mut lines, &header)?;
+                    lines.push(b'\n');
+                    file
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    OpenOptions::new().append(true).open(&path)?
+                }
+                Err(e) => return Err(e),
+            };
+            serde_json::to_writer(&mut lines, entry)?;
+            lines.push(b'\n');
+            file.write_all(&lines)
+        };
+        write().map_err(|e| Error::io(format!("Could not write recording {}", path.display()), e))
+    }
+}
+
+/// A game as recorded by `GameRecorder`. Games that were cut short may lack a
+/// start or an end.
+#[derive(Debug)]
+pub struct Recording {
+    pub snake: String,
+    pub start: Option<StartRequest>,
+    pub turns: Vec<RecordedTurn>,
+    pub end: Option<EndRequest>,
+}
+
+/// A `/move` request and how we answered it.
+#[derive(Debug)]
+pub struct RecordedTurn {
+    pub request: MoveRequest,
+    pub response: MoveResponse,
+}
+
+impl RecordedTurn {
+    /// The board as the player saw it on this turn.
+    pub fn game_state(&self) -> Result<GameState> {
+        GameState::from_board(&self.request.board, &self.request.you.id)
+    }
+
+    pub fn player_move(&self) -> Move {
+        self.response.move_direction.into()
+    }
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| Error::io(format!("Could not open recording {}", path.display()), e))?;
+        Self::read(BufReader::new(file))
+            .map_err(|e| Error::parse(format!("Could not load recording {}", path.display()), e))
+    }
+
+    /// Reads a recording from JSON lines.
+    pub fn read(reader: impl BufRead) -> Result<Self> {
+        let mut lines = reader.lines().enumerate();
+        let mut entry = || -> Option<Result<Entry>> {
+            let (i, line) = lines.next()?;
+            Some(
+                line.map_err(|e| Error::io(format!("line {}", i + 1), e))
+                    .and_then(|line| {
+                        serde_json::from_str(&line)
+                            .map_err(|e| Error::parse(format!("line {}", i + 1), e))
+                    }),
+            )
+        };
+        let snake = match entry().transpose()? {
+            Some(Entry::Header { version, snake }) if version == RECORDING_VERSION => snake,
+            Some(Entry::Header { version, .. }) => {
+                return Err(Error::invalid_text(format!(
+                    "unsupported recording version {}, expected {}",
+                    version, RECORDING_VERSION
+                )));
+            }
+            _ => return Err(Error::invalid_text("missing recording header")),
+        };
+        let mut recording = Recording {
+            snake,
+            start: None,
+            turns: vec![],
+            end: None,
+        };
+        while let Some(next) = entry().transpose()? {
+            match next {
+                Entry::Header { .. } => return Err(Error::invalid_text("more than one header")),
+                Entry::Start { request } => recording.start = Some(request),
+                Entry::Move { request, response } => {
+                    recording.turns.push(RecordedTurn { request, response })
+                }
+                Entry::End { request } => recording.end = Some(request),
+            }
+        }
+        Ok(recording)
+    }
+
+    /// The board on each recorded turn, as the player saw it.
+    pub fn game_states(&self) -> Result<Vec<GameState>> {
+        self.turns.iter().map(RecordedTurn::game_state).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_types::{Battlesnake, Board, Coordinates, Game};
+
+    fn request_parts(turn: i32) -> (Game, Board, Battlesnake) {
+        let body: Vec<_> = (0..3)
+            .map(|i| Coordinates {
+                x: 1 + turn,
+                y: 1 + i,
+            })
+            .collect();
+        let you = Battlesnake {
+            id: "you".into(),
+            name: "you".into(),
+            health: 100,
+            head: body[0].clone(),
+            length: 3,
+            body,
+            latency: "".into(),
+            shout: "".into(),
+            squad: "".into(),
+            customizations: serde_json::Value::Null,
+        };
+        let game = Game {
+            id: "game/1".into(),
+            ruleset: serde_json::Value::Null,
+            map: "standard".into(),
+            timeout: 500,
+            source: "custom".into(),
+        };
+        let board = Board {
+            height: 11,
+            width: 11,
+            food: vec![Coordinates { x: 5, y: 5 }],
+            hazards: vec![],
+            snakes: vec![you.clone()],
+        };
+        (game, board, you)
+    }
+
+    #[test]
+    fn recordings_round_trip() {
+        let dir = std::env::temp_dir().join("blaze-haskell-recording-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let recorder = GameRecorder::new(&dir);
+        let (game, board, you) = request_parts(0);
+        recorder
+            .record_start(
+                "default",
+                &StartRequest {
+                    game,
+                    turn: 0,
+                    board,
+                    you,
+                },
+            )
+            .unwrap();
+        for turn in 0..2 {
+            let (game, board, you) = request_parts(turn);
+            let request = MoveRequest {
+                game,
+                turn: turn as u32,
+                board,
+                you,
+            };
+            recorder
+                .record_move("default", &request, &Move::Right.into())
+                .unwrap();
+        }
+        let (game, board, you) = request_parts(2);
+        recorder
+            .record_end(
+                "default",
+                &EndRequest {
+                    game,
+                    turn: 2,
+                    board,
+                    you,
+                },
+            )
+            .unwrap();
+
+        let path = recorder.path("game/1", "default");
+        let recording = Recording::load(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(recording.snake, "default");
+        assert!(recording.start.is_some());
+        assert_eq!(recording.end.as_ref().unwrap().turn, 2);
+        assert_eq!(recording.turns.len(), 2);
+        assert_eq!(recording.turns[1].player_move(), Move::Right);
+        let game_states = recording.game_states().unwrap();
+        assert_eq!(
+            game_states[1].player.head(),
+            Some(crate::game_state::Cell(2, 1))
+        );
+        assert_eq!(game_states[1].food.len(), 1);
+    }
+
+    #[test]
+    fn recordings_of_other_versions_are_refused() {
+        let header = "{\"type\":\"header\",\"version\":99,\"snake\":\"default\"}\n";
+        assert!(Recording::read(header.as_bytes()).is_err());
+        assert!(Recording::read("".as_bytes()).is_err());
+    }
+}
@@ -1,26 +1,189 @@
 use {
     axum::{
         Json, Router,
+        extract::{MatchedPath, Request, State},
+        http::{StatusCode, header},
+        middleware::{self, Next},
+        response::{IntoResponse, Response},
         routing::{get, post},
     },
     log::info,
-    serde::{Deserialize, Serialize},
+    serde::Serialize,
+    std::{
+        collections::{BTreeMap, HashMap},
+        sync::{
+            Arc, Mutex,
+            atomic::{AtomicBool, Ordering},
+        },
+        time::{Duration, Instant},
+    },
+    tokio::{sync::mpsc, task::JoinSet},
 };
 
 use blaze_haskell::{
-    api_types::{Battlesnake, Board, Game},
-    error::Result,
-    game_state,
+    api_types::{EndRequest, MoveRequest, MoveResponse, StartRequest},
+    config::{Config, SnakeConfig, TimingConfig},
+    decision_log::{Decision, DecisionLog},
+    error::{Error, Result},
     game_state::GameState,
+    metrics::{self, GameResult, Metrics},
     planner,
+    planner::{PlannerConfig, SearchTree},
+    recording::GameRecorder,
+    session::SessionRegistry,
+    shout,
 };
 
 static BATTLESNAKE_API_VERSION: &str = "1";
-static AUTHOR: &str = "Matt Gordon";
-static SNAKE_VERSION: &str = "pre-alpha";
-static SNAKE_COLOR: &str = "#00ff00";
-static SNAKE_HEAD: &str = "default";
-static SNAKE_TAIL: &str = "default";
+static SESSION_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Clone)]
+struct AppState {
+    /// Labels this snake's metrics: its prefix, or "default" at the root.
+    name: Arc<str>,
+    snake: Arc<SnakeConfig>,
+    planner: PlannerConfig,
+    search: planner::SearchFn,
+    timing: Arc<TimingConfig>,
+    sessions: Arc<SessionRegistry>,
+    metrics: Arc<Metrics>,
+    decision_log: Option<DecisionLog>,
+    recorder: Option<GameRecorder>,
+    lifecycle: Lifecycle,
+}
+
+/// What the routes share with the server as it starts and shuts down: whether
+/// it is taking on new games, the games in progress and their logs still
+/// being written.
+#[derive(Clone, Default)]
+pub struct Lifecycle {
+    ready: Arc<AtomicBool>,
+    sessions: Arc<Mutex<Vec<Arc<SessionRegistry>>>>,
+    log_writes: LogWrites,
+}
+
+impl Lifecycle {
+    /// Sets whether `/readyz` reports ready and `/start` takes on games.
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::Relaxed);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// How many games the snakes are still playing, not counting those that
+    /// have gone quiet for longer than a session lasts.
+    pub fn live_games(&self) -> usize {
+        let now = Instant::now();
+        self.sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|sessions| {
+                sessions.evict_expired(now);
+                sessions.len()
+            })
+            .sum()
+    }
+
+    /// Waits for every game log write queued so far to finish.
+    pub async fn flush_logs(&self) {
+        self.log_writes.flush().await
+    }
+
+    fn track(&self, sessions: &Arc<SessionRegistry>) {
+        self.sessions.lock().unwrap().push(sessions.clone());
+    }
+}
+
+/// A write to a game's logs, run on a blocking thread.
+type LogWrite = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Writes the decision logs and recordings in the background, through one
+/// writer per game and snake so that each file gets its lines in the order
+/// the requests arrived. Shutdown waits for the writes still queued.
+#[derive(Clone, Default)]
+struct LogWrites(Arc<Mutex<Writers>>);
+
+#[derive(Default)]
+struct Writers {
+    games: HashMap<(Arc<str>, String), mpsc::UnboundedSender<LogWrite>>,
+    tasks: JoinSet<()>,
+}
+
+impl LogWrites {
+    /// Queues `write` behind the earlier writes for `game_id` as played by
+    /// `snake`.
+    fn write(
+        &self,
+        snake: &Arc<str>,
+        game_id: &str,
+        write: impl FnOnce() -> Result<()> + Send + 'static,
+    ) {
+        let mut writers = self.0.lock().unwrap();
+        // Forget the writers that have finished.
+        while writers.tasks.try_join_next().is_some() {}
+        let key = (snake.clone(), game_id.to_owned());
+        let write: LogWrite = match writers.games.get(&key) {
+            Some(writer) => match writer.send(Box::new(write)) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(write)) => write,
+            },
+            None => Box::new(write),
+        };
+        let (writer, queue) = mpsc::unbounded_channel();
+        writer.send(write).unwrap();
+        writers.games.insert(key.clone(), writer);
+        let this = self.clone();
+        writers.tasks.spawn(this.run_writer(key, queue));
+    }
+
+    /// Closes the writer for `game_id` as played by `snake` once the writes
+    /// queued so far are done.
+    fn finish(&self, snake: &Arc<str>, game_id: &str) {
+        let key = (snake.clone(), game_id.to_owned());
+        self.0.lock().unwrap().games.remove(&key);
+    }
+
+    /// Runs the writes for one game in order, until the game is finished or
+    /// has gone quiet for longer than a session lasts.
+    async fn run_writer(
+        self,
+        key: (Arc<str>, String),
+        mut queue: mpsc::UnboundedReceiver<LogWrite>,
+    ) {
+        loop {
+            match tokio::time::timeout(SESSION_TTL, queue.recv()).await {
+                Ok(Some(write)) => match tokio::task::spawn_blocking(write).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => e.log(),
+                    Err(e) => Error::internal("A game log write failed", e).log(),
+                },
+                Ok(None) => return,
+                Err(_) => {
+                    // Writes are only queued under the lock, so none can
+                    // slip in between checking the queue and dropping it.
+                    let mut writers = self.0.lock().unwrap();
+                    if queue.is_empty() {
+                        writers.games.remove(&key);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Waits for every write queued so far to finish.
+    async fn flush(&self) {
+        let mut tasks = {
+            let mut writers = self.0.lock().unwrap();
+            writers.games.clear();
+            std::mem::take(&mut writers.tasks)
+        };
+        while tasks.join_next().await.is_some() {}
+    }
+}
 
 #[derive(Serialize)]
 struct SnakeDetails {
@@ -32,13 +195,13 @@ struct SnakeDetails {
     version: Option<String>,
 }
 
-async fn describe_snake() -> Json<SnakeDetails> {
+async fn describe_snake(State(state): State<AppState>) -> Json<SnakeDetails> {
     let apiversion = BATTLESNAKE_API_VERSION.into();
-    let author = Some(AUTHOR.into());
-    let color = Some(SNAKE_COLOR.into());
-    let head = Some(SNAKE_HEAD.into());
-    let tail = Some(SNAKE_TAIL.into());
-    let version = Some(SNAKE_VERSION.into());
+    let author = Some(state.snake.author.clone());
+    let color = Some(state.snake.color.clone());
+    let head = Some(state.snake.head.clone());
+    let tail = Some(state.snake.tail.clone());
+    let version = Some(state.snake.version.clone());
     Json(SnakeDetails {
         apiversion,
         author,
@@ -49,95 +212,459 @@ async fn describe_snake() -> Json<SnakeDetails> {
     })
 }
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct StartRequest {
-    game: Game,
-    turn: i32,
-    board: Board,
-    you: Battlesnake,
+/// Takes on the game unless the server is shutting down, in which case it
+/// answers 503.
+async fn post_start(State(state): State<AppState>, request: Json<StartRequest>) -> StatusCode {
+    if !state.lifecycle.is_ready() {
+        info!(
+            "Turned down game with id {} while shutting down.",
+            request.game.id
+        );
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+    info!("Started new game with id{}.", request.game.id);
+    state.sessions.start(&request.game.id);
+    let game_id = request.game.id.clone();
+    record_in_background(&state, &game_id, move |recorder, name| {
+        recorder.record_start(name, &request)
+    });
+    StatusCode::OK
 }
 
-async fn post_start(request: Json<StartRequest>) -> () {
-    info!("Started new game with id{}.", request.game.id);
+async fn post_move(
+    State(state): State<AppState>,
+    request: Json<MoveRequest>,
+) -> Result<Json<MoveResponse>> {
+    let start = Instant::now();
+    info!("Receive request for turn {} move.", request.turn);
+    let game_state = GameState::from_board(&request.board, &request.you.id)?;
+    info!("Latency on last turn was {}.", request.you.latency);
+    state
+        .metrics
+        .record_reported_latency(&state.name, &request.you.latency);
+    let shout_state = state.snake.shout.then(|| game_state.clone());
+    let turn = planner::plan_turn(
+        &state.sessions,
+        &request,
+        game_state,
+        state.planner,
+        Duration::from_millis(state.timing.latency_margin_ms),
+        start,
+        state.search,
+    )
+    .await;
+    state
+        .metrics
+        .record_move(&state.name, turn.duration, turn.plan.as_ref());
+    let shout = match &turn.plan {
+        Some(plan) => {
+            if let Some(decision_log) = state.decision_log.clone() {
+                let decision =
+                    Decision::new(request.turn, request.board.clone(), plan, turn.duration);
+                let game_id = request.game.id.clone();
+                let name = state.name.clone();
+                state
+                    .lifecycle
+                    .log_writes
+                    .write(&state.name, &request.game.id, move || {
+                        decision_log.record(&game_id, &name, &decision)
+                    });
+            }
+            shout_state
+                .and_then(|game_state| shout::shout(&request, &game_state, plan, &state.planner))
+        }
+        None => {
+            state.metrics.record_fallback(&state.name);
+            shout_state.map(|_| shout::FALLBACK_SHOUT.into())
+        }
+    };
+    let response = MoveResponse {
+        shout,
+        ..turn.player_move.into()
+    };
+    let recorded = response.clone();
+    let game_id = request.game.id.clone();
+    record_in_background(&state, &game_id, move |recorder, name| {
+        recorder.record_move(name, &request, &recorded)
+    });
+    Ok(Json(response))
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "kebab-case")]
-#[allow(dead_code)]
-enum MoveDirection {
-    Up,
-    Down,
-    Left,
-    Right,
+async fn post_end(State(state): State<AppState>, body: Json<EndRequest>) -> () {
+    info!("Game with id {} ended.", body.game.id);
+    state.sessions.end(&body.game.id);
+    state.metrics.record_game(
+        &state.name,
+        GameResult::from_board(&body.board, &body.you.id),
+    );
+    let game_id = body.game.id.clone();
+    record_in_background(&state, &game_id, move |recorder, name| {
+        recorder.record_end(name, &body)
+    });
+    state.lifecycle.log_writes.finish(&state.name, &game_id);
 }
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct MoveRequest {
-    game: Game,
-    turn: u32,
-    board: Board,
-    you: Battlesnake,
+/// Writes to the recording of `game_id`, if there is one, without holding up
+/// the response.
+fn record_in_background(
+    state: &AppState,
+    game_id: &str,
+    record: impl FnOnce(&GameRecorder, &str) -> Result<()> + Send + 'static,
+) {
+    if let Some(recorder) = state.recorder.clone() {
+        let name = state.name.clone();
+        state
+            .lifecycle
+            .log_writes
+            .write(&state.name, game_id, move || record(&recorder, &name));
+    }
 }
 
-#[derive(Serialize)]
-struct MoveResponse {
-    #[serde(rename = "move")]
-    move_direction: MoveDirection,
-    shout: Option<String>,
-}
-
-impl From<game_state::Move> for MoveResponse {
-    fn from(value: game_state::Move) -> Self {
-        match value {
-            game_state::Move::Up => MoveResponse {
-                move_direction: MoveDirection::Up,
-                shout: None,
-            },
-            game_state::Move::Down => MoveResponse {
-                move_direction: MoveDirection::Down,
-                shout: None,
-            },
-            game_state::Move::Left => MoveResponse {
-                move_direction: MoveDirection::Left,
-                shout: None,
-            },
-            game_state::Move::Right => MoveResponse {
-                move_direction: MoveDirection::Right,
-                shout: None,
-            },
+/// How the server was built and is set up, as served on `/version`.
+#[derive(Clone, Serialize)]
+struct BuildDetails {
+    version: &'static str,
+    git_commit: &'static str,
+    profile: &'static str,
+    features: Vec<&'static str>,
+    planner: PlannerConfig,
+    /// The planner settings of each personality, by prefix.
+    personalities: BTreeMap<String, PlannerConfig>,
+}
+
+impl BuildDetails {
+    fn new(config: &Config) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: env!("BUILD_GIT_COMMIT"),
+            profile: env!("BUILD_PROFILE"),
+            features: env!("BUILD_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .collect(),
+            planner: config.planner,
+            personalities: config
+                .personalities
+                .iter()
+                .map(|personality| (personality.prefix.clone(), personality.planner))
+                .collect(),
         }
     }
 }
 
-async fn post_move(request: Json<MoveRequest>) -> Result<Json<MoveResponse>> {
-    info!("Receive request for turn {} move.", request.turn);
-    info!("Latency on last turn was {}.", request.you.latency);
-    Ok(Json(
-        planner::devise_plan(GameState::from_board(&request.board, &request.you.id)?)
-            .await
-            .into(),
-    ))
+async fn get_version(State(details): State<Arc<BuildDetails>>) -> Json<BuildDetails> {
+    Json(details.as_ref().clone())
 }
 
-#[derive(Deserialize)]
-#[allow(dead_code)]
-struct EndRequest {
-    game: Game,
-    turn: i32,
-    board: Board,
-    you: Battlesnake,
+/// Liveness: answers as long as the server is running at all.
+async fn get_healthz() -> &'static str {
+    "ok"
 }
 
-async fn post_end(body: Json<EndRequest>) -> () {
-    info!("Game with id {} ended.", body.game.id)
+/// Readiness: whether the server is taking on games, which it stops doing
+/// as soon as it starts draining for shutdown.
+async fn get_readyz(State(lifecycle): State<Lifecycle>) -> (StatusCode, &'static str) {
+    if lifecycle.is_ready() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+    }
 }
 
-pub fn router() -> Router {
+async fn get_metrics(State(metrics): State<Arc<Metrics>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, metrics::CONTENT_TYPE)],
+        metrics.encode(),
+    )
+}
+
+/// Counts every request by the route it matched.
+async fn count_requests(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched".into(), |path| path.as_str().to_owned());
+    let response = next.run(request).await;
+    metrics.record_request(&route, response.status().as_u16());
+    response
+}
+
+/// Serves the top-level snake at the root and each personality under its
+/// own prefix, along with `/metrics`, `/version` and the health checks for all
+/// of them. `/readyz` and `/start` follow whether `lifecycle` is ready, which
+/// also tracks the games in progress and their logs.
+pub fn router(config: &Config, lifecycle: &Lifecycle) -> Router {
+    let metrics = Arc::new(Metrics::new());
+    config
+        .personalities
+        .iter()
+        .fold(
+            snake_router(
+                "default",
+                &config.snake,
+                &config.planner,
+                config,
+                &metrics,
+                lifecycle,
+            ),
+            |router, personality| {
+                router.nest(
+                    &format!("/{}", personality.prefix),
+                    snake_router(
+                        &personality.prefix,
+                        &personality.snake,
+                        &personality.planner,
+                        config,
+                        &metrics,
+                        lifecycle,
+                    ),
+                )
+            },
+        )
+        .route("/metrics", get(get_metrics).with_state(metrics.clone()))
+        .route(
+            "/version",
+            get(get_version).with_state(Arc::new(BuildDetails::new(config))),
+        )
+        .route("/healthz", get(get_healthz))
+        .route("/readyz", get(get_readyz).with_state(lifecycle.clone()))
+        .layer(middleware::from_fn_with_state(metrics, count_requests))
+}
+
+fn snake_router(
+    name: &str,
+    snake: &SnakeConfig,
+    planner: &PlannerConfig,
+    config: &Config,
+    metrics: &Arc<Metrics>,
+    lifecycle: &Lifecycle,
+) -> Router {
     Router::new()
         .route("/", get(describe_snake))
         .route("/start", post(post_start))
         .route("/move", post(post_move))
         .route("/end", post(post_end))
+        .with_state(AppState::new(
+            name, snake, planner, config, metrics, lifecycle,
+        ))
+}
+
+impl AppState {
+    fn new(
+        name: &str,
+        snake: &SnakeConfig,
+        planner: &PlannerConfig,
+        config: &Config,
+        metrics: &Arc<Metrics>,
+        lifecycle: &Lifecycle,
+    ) -> Self {
+        let sessions = Arc::new(SessionRegistry::new(SESSION_TTL));
+        lifecycle.track(&sessions);
+        Self {
+            name: name.into(),
+            snake: Arc::new(snake.clone()),
+            planner: *planner,
+            search: SearchTree::find_cancellable_plan,
+            timing: Arc::new(config.timing.clone()),
+            sessions,
+            metrics: metrics.clone(),
+            decision_log: config
+                .logging
+                .decision_log_dir
+                .as_deref()
+                .map(DecisionLog::new),
+            recorder: config
+                .logging
+                .recording_dir
+                .as_deref()
+                .map(GameRecorder::new),
+            lifecycle: lifecycle.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        blaze_haskell::{game_state::Move, recording::Recording, shout::FALLBACK_SHOUT},
+        std::sync::atomic::AtomicBool,
+        tower::ServiceExt,
+    };
+
+    fn move_request(timeout: i32) -> MoveRequest {
+        let you = serde_json::json!({
+            "id": "you", "name": "you", "health": 90, "head": {"x": 0, "y": 5},
+            "length": 3, "body": [{"x": 0, "y": 5}, {"x": 1, "y": 5}, {"x": 2, "y": 5}],
+            "latency": "0", "shout": "", "squad": "", "customizations": {}
+        });
+        serde_json::from_value(serde_json::json!({
+            "game": {"id": "g", "ruleset": {}, "map": "standard", "timeout": timeout, "source": ""},
+            "turn": 3,
+            "board": {"width": 11, "height": 11, "food": [], "hazards": [], "snakes": [you]},
+            "you": you
+        }))
+        .unwrap()
+    }
+
+    /// A snake that shouts, searching with `search`.
+    fn app_state(search: planner::SearchFn) -> AppState {
+        let snake = SnakeConfig {
+            shout: true,
+            ..SnakeConfig::default()
+        };
+        let config = Config {
+            timing: TimingConfig {
+                latency_margin_ms: 0,
+            },
+            ..Config::default()
+        };
+        AppState {
+            search,
+            ..AppState::new(
+                "default",
+                &snake,
+                &config.planner,
+                &config,
+                &Arc::new(Metrics::new()),
+                &Lifecycle::default(),
+            )
+        }
+    }
+
+    /// Answers `request` as `/move` would, expecting the fallback move.
+    fn assert_falls_back(state: AppState, request: MoveRequest) {
+        let game_state = GameState::from_board(&request.board, &request.you.id).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let Json(response) = runtime
+            .block_on(post_move(State(state.clone()), Json(request)))
+            .unwrap();
+        assert_eq!(
+            response,
+            MoveResponse {
+                shout: Some(FALLBACK_SHOUT.into()),
+                ..planner::fallback_move(&game_state).into()
+            }
+        );
+        assert!(
+            state
+                .metrics
+                .encode()
+                .contains(r#"blaze_haskell_fallback_moves_total{snake="default"} 1"#)
+        );
+        assert!(
+            state
+                .metrics
+                .encode()
+                .contains(r#"blaze_haskell_move_duration_seconds_count{snake="default"} 1"#)
+        );
+    }
+
+    #[test]
+    fn move_falls_back_when_the_planner_panics() {
+        let panics: planner::SearchFn = |_, _, _, _, _| panic!("planner bug");
+        assert_falls_back(app_state(panics), move_request(500));
+    }
+
+    #[test]
+    fn move_recovers_after_a_panic_mid_turn() {
+        let state = app_state(SearchTree::find_cancellable_plan);
+        let request = move_request(500);
+        let panicked = std::panic::catch_unwind(|| {
+            state.sessions.with_session(&request.game.id, |_| {
+                panic!("bug while updating the session")
+            })
+        });
+        assert!(panicked.is_err());
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        assert!(
+            runtime
+                .block_on(post_move(State(state.clone()), Json(request)))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn move_falls_back_and_cancels_the_search_when_it_overruns() {
+        static CANCELLED: AtomicBool = AtomicBool::new(false);
+        let overruns: planner::SearchFn = |_, _, _, _, cancelled| {
+            let give_up = Instant::now() + Duration::from_secs(5);
+            while !cancelled.load(Ordering::Relaxed) && Instant::now() < give_up {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            CANCELLED.store(cancelled.load(Ordering::Relaxed), Ordering::Relaxed);
+            None
+        };
+        assert_falls_back(app_state(overruns), move_request(50));
+        let give_up = Instant::now() + Duration::from_secs(5);
+        while !CANCELLED.load(Ordering::Relaxed) && Instant::now() < give_up {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(CANCELLED.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn log_writes_keep_each_game_in_order() {
+        let dir = std::env::temp_dir().join("blaze-haskell-log-writes-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        let recorder = GameRecorder::new(&dir);
+        let log_writes = LogWrites::default();
+        let snake: Arc<str> = "default".into();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            for turn in 0..50 {
+                let recorder = recorder.clone();
+                let request = MoveRequest {
+                    turn,
+                    ..move_request(500)
+                };
+                log_writes.write(&snake, "g", move || {
+                    recorder.record_move("default", &request, &Move::Up.into())
+                });
+            }
+            log_writes.finish(&snake, "g");
+            log_writes.flush().await;
+        });
+
+        let recording = Recording::load(&recorder.path("g", "default")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        let turns: Vec<_> = recording
+            .turns
+            .iter()
+            .map(|turn| turn.request.turn)
+            .collect();
+        assert_eq!(turns, (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn readyz_and_start_turn_away_games_when_not_ready() {
+        let lifecycle = Lifecycle::default();
+        let app = router(&Config::default(), &lifecycle);
+        let start = serde_json::to_vec(&move_request(500)).unwrap();
+        let status = |method: &str, uri: &str| {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(start.clone()))
+                .unwrap();
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime
+                .block_on(app.clone().oneshot(request))
+                .unwrap()
+                .status()
+        };
+
+        lifecycle.set_ready(true);
+        assert_eq!(status("GET", "/readyz"), StatusCode::OK);
+        assert_eq!(status("POST", "/start"), StatusCode::OK);
+        lifecycle.set_ready(false);
+        assert_eq!(status("GET", "/readyz"), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("POST", "/start"), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status("POST", "/move"), StatusCode::OK);
+    }
 }
@@ -0,0 +1,250 @@
+use {
+    log::{info, warn},
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex, MutexGuard},
+        time::{Duration, Instant},
+    },
+};
+
+use crate::{
+    api_types::Board,
+    game_state::GameState,
+    planner::{Behaviour, OpponentModel, SearchTree},
+};
+
+/// Everything we remember about a game in progress between requests.
+#[derive(Debug, Default)]
+pub struct GameSession {
+    pub opponent_model: OpponentModel,
+    /// The planner's tree from the previous turn.
+    pub search_tree: Option<SearchTree>,
+    /// How long the planner took on each turn so far.
+    pub move_durations: Vec<Duration>,
+}
+
+impl GameSession {
+    /// Learns from `board`, the latest board of the game, and returns the
+    /// tree to search from it along with the enemies' expected behaviour. The
+    /// previous turn's tree is reused if it foresaw `game_state`.
+    pub fn prepare_search(
+        &mut self,
+        board: &Board,
+        player_id: &str,
+        game_state: GameState,
+    ) -> (SearchTree, Vec<Behaviour>) {
+        self.opponent_model.observe(board);
+        let opponents = self.opponent_model.behaviours(board, player_id);
+        let tree = match self
+            .search_tree
+            .take()
+            .and_then(|tree| tree.advance(&game_state))
+        {
+            Some(tree) => tree,
+            None => {
+                info!("Starting a fresh search tree.");
+                SearchTree::new(game_state)
+            }
+        };
+        (tree, opponents)
+    }
+}
+
+/// Locks `session`, starting it afresh if a panic while it was in use left it
+/// poisoned, so that one bad turn doesn't fail every later turn of the game.
+fn lock(session: &Mutex<GameSession>) -> MutexGuard<'_, GameSession> {
+    session.lock().unwrap_or_else(|poisoned| {
+        warn!("Starting the session afresh after a panic while it was in use.");
+        session.clear_poison();
+        let mut session = poisoned.into_inner();
+        *session = GameSession::default();
+        session
+    })
+}
+
+#[derive(Debug)]
+struct Entry {
+    session: Arc<Mutex<GameSession>>,
+    last_active: Instant,
+}
+
+impl Entry {
+    fn new(now: Instant) -> Self {
+        Self {
+            session: Arc::default(),
+            last_active: now,
+        }
+    }
+}
+
+/// The sessions of all games in progress, keyed by `Game::id`.
+///
+/// Sessions are created on `/start` (or on the first `/move` if we missed the
+/// start) and dropped on `/end`. Games that never send `/end` are evicted
+/// once they have been idle for longer than the time-to-live.
+///
+/// Each session has a lock of its own, so that a slow turn in one game never
+/// holds up the others; the registry's lock is only held to look sessions up.
+#[derive(Debug)]
+pub struct SessionRegistry {
+    sessions: Mutex<HashMap<String, Entry>>,
+    ttl: Duration,
+}
+
+impl SessionRegistry {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    pub fn start(&self, game_id: &str) {
+        let now = Instant::now();
+        self.evict_expired(now);
+        // Free any session replaced after letting go of the registry.
+        let replaced = self
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(game_id.into(), Entry::new(now));
+        drop(replaced);
+    }
+
+    /// Runs `f` on the session for `game_id`, creating it if need be.
+    pub fn with_session<R>(&self, game_id: &str, f: impl FnOnce(&mut GameSession) -> R) -> R {
+        let now = Instant::now();
+        self.evict_expired(now);
+        let session = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let entry = sessions
+                .entry(game_id.into())
+                .or_insert_with(|| Entry::new(now));
+            entry.last_active = now;
+            entry.session.clone()
+        };
+        f(&mut lock(&session))
+    }
+
+    /// Runs `f` on the session for `game_id` if there still is one, without
+    /// bringing back a game that has ended or been evicted.
+    pub fn with_existing_session<R>(
+        &self,
+        game_id: &str,
+        f: impl FnOnce(&mut GameSession) -> R,
+    ) -> Option<R> {
+        let now = Instant::now();
+        let session = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let entry = sessions.get_mut(game_id)?;
+            entry.last_active = now;
+            entry.session.clone()
+        };
+        Some(f(&mut lock(&session)))
+    }
+
+    pub fn end(&self, game_id: &str) -> Option<GameSession> {
+        let entry = self.sessions.lock().unwrap().remove(game_id)?;
+        // A turn of the game may still be in progress, holding the session.
+        Some(match Arc::try_unwrap(entry.session) {
+            Ok(session) => session.into_inner().unwrap_or_default(),
+            Err(session) => std::mem::take(&mut lock(&session)),
+        })
+    }
+
+    /// Drops every session that has been idle for longer than the
+    /// time-to-live as of `now`, returning how many were dropped. Their search
+    /// trees may be large, so they are freed after letting go of the registry.
+    pub fn evict_expired(&self, now: Instant) -> usize {
+        let expired: Vec<_> = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let ids: Vec<_> = sessions
+                .iter()
+                .filter(|(_, entry)| now.saturating_duration_since(entry.last_active) > self.ttl)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| sessions.remove(id)).collect()
+        };
+        expired.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_lifecycle() {
+        let target = SessionRegistry::new(Duration::from_secs(60));
+        target.start("game-one");
+        assert_eq!(target.len(), 1);
+        target.with_session("game-one", |session| {
+            session.move_durations.push(Duration::from_millis(5))
+        });
+        target.with_session("game-two", |session| {
+            assert!(session.move_durations.is_empty())
+        });
+        assert_eq!(target.len(), 2);
+        let session = target.end("game-one").unwrap();
+        assert_eq!(session.move_durations, vec![Duration::from_millis(5)]);
+        assert!(target.end("game-one").is_none());
+        assert_eq!(target.len(), 1);
+    }
+
+    #[test]
+    fn sessions_lock_independently() {
+        let target = SessionRegistry::new(Duration::from_secs(60));
+        target.with_session("game-one", |_| {
+            target.with_session("game-two", |session| {
+                session.move_durations.push(Duration::from_millis(5))
+            });
+            assert_eq!(target.len(), 2);
+        });
+        let session = target.end("game-two").unwrap();
+        assert_eq!(session.move_durations, vec![Duration::from_millis(5)]);
+    }
+
+    #[test]
+    fn sessions_poisoned_by_a_panic_start_afresh() {
+        let target = SessionRegistry::new(Duration::from_secs(60));
+        let panicked = std::panic::catch_unwind(|| {
+            target.with_session("game-one", |session| {
+                session.move_durations.push(Duration::from_millis(5));
+                panic!("half way through a turn");
+            })
+        });
+        assert!(panicked.is_err());
+        target.with_session("game-one", |session| {
+            assert!(session.move_durations.is_empty());
+        });
+    }
+
+    #[test]
+    fn ended_sessions_are_not_brought_back() {
+        let target = SessionRegistry::new(Duration::from_secs(60));
+        target.start("game-one");
+        assert_eq!(target.with_existing_session("game-one", |_| 1), Some(1));
+        target.end("game-one");
+        assert_eq!(target.with_existing_session("game-one", |_| 1), None);
+        assert!(target.is_empty());
+    }
+
+    #[test]
+    fn idle_sessions_are_evicted() {
+        let target = SessionRegistry::new(Duration::from_secs(60));
+        target.start("game-one");
+        let now = Instant::now();
+        assert_eq!(target.evict_expired(now), 0);
+        assert_eq!(target.evict_expired(now + Duration::from_secs(30)), 0);
+        assert_eq!(target.evict_expired(now + Duration::from_secs(61)), 1);
+        assert!(target.is_empty());
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{
+    api_types::MoveRequest,
+    game_state::{Cell, GameState},
+    planner::{Plan, PlannerConfig},
+};
+
+/// The longest shout the game server accepts, in characters.
+static MAX_SHOUT_LENGTH: usize = 256;
+/// At or below this health the snake says it is looking for food.
+static LOW_HEALTH: u8 = 30;
+
+/// What to shout when answering without a plan.
+pub static FALLBACK_SHOUT: &str = "out of time: taking any safe move";
+
+/// Sums up what the planner is after with `plan` for `request`, whose board
+/// is `game_state`: escaping a trap, hunting a snake, food, or nothing in
+/// particular. Returns `None` if the player has no head to move.
+pub fn shout(
+    request: &MoveRequest,
+    game_state: &GameState,
+    plan: &Plan,
+    config: &PlannerConfig,
+) -> Option<String> {
+    let head = game_state.player.head()?;
+    let next = head + plan.player_move;
+    let approaches = |target: Cell| distance(next, target) < distance(head, target);
+
+    let intent = if plan
+        .move_scores
+        .iter()
+        .all(|&(_, score)| score <= config.weights.lose / 2.0)
+    {
+        "trapped: maximizing survival".into()
+    } else if let Some(prey) = config
+        .aggressive
+        .then(|| nearest_prey(game_state, request, head))
+        .flatten()
+        .filter(|&(prey_head, _)| approaches(prey_head))
+    {
+        format!("hunting {}", prey.1)
+    } else if request.you.health <= LOW_HEALTH
+        && game_state
+            .food
+            .iter()
+            .copied()
+            .min_by_key(|&food| distance(head, food))
+            .is_some_and(approaches)
+    {
+        "low health, seeking food".into()
+    } else {
+        format!("looking {} turns ahead", plan.search_depth)
+    };
+    Some(truncate(intent))
+}
+
+/// The head and name of the closest snake shorter than the player.
+fn nearest_prey<'a>(
+    game_state: &GameState,
+    request: &'a MoveRequest,
+    head: Cell,
+) -> Option<(Cell, &'a str)> {
+    // The game state lists enemies in the order of the board, without the
+    // player.
+    let enemies = request
+        .board
+        .snakes
+        .iter()
+        .filter(|snake| snake.id != request.you.id);
+    game_state
+        .enemies
+        .iter()
+        .zip(enemies)
+        .filter(|(enemy, _)| enemy.length() < game_state.player.length())
+        .filter_map(|(enemy, api_snake)| Some((enemy.head()?, api_snake.name.as_str())))
+        .min_by_key(|&(prey_head, _)| distance(head, prey_head))
+}
+
+fn distance(Cell(x, y): Cell, Cell(to_x, to_y): Cell) -> i32 {
+    (i32::from(x) - i32::from(to_x)).abs() + (i32::from(y) - i32::from(to_y)).abs()
+}
+
+fn truncate(shout: String) -> String {
+    if shout.chars().count() > MAX_SHOUT_LENGTH {
+        shout.chars().take(MAX_SHOUT_LENGTH).collect()
+    } else {
+        shout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_state::Move;
+
+    fn snake(name: &str, health: u8, body: &[(i32, i32)]) -> serde_json::Value {
+        let body: Vec<_> = body
+            .iter()
+            .map(|&(x, y)| serde_json::json!({"x": x, "y": y}))
+            .collect();
+        serde_json::json!({
+            "id": name, "name": name, "health": health, "head": body[0],
+            "length": body.len(), "body": body, "latency": "0", "shout": "",
+            "squad": "", "customizations": {}
+        })
+    }
+
+    fn request(you: serde_json::Value, enemies: Vec<serde_json::Value>) -> MoveRequest {
+        let mut snakes = vec![you.clone()];
+        snakes.extend(enemies);
+        serde_json::from_value(serde_json::json!({
+            "game": {"id": "g", "ruleset": {}, "map": "standard", "timeout": 500, "source": ""},
+            "turn": 10,
+            "board": {"width": 11, "height": 11, "food": [{"x": 0, "y": 5}], "hazards": [],
+                      "snakes": snakes},
+            "you": you
+        }))
+        .unwrap()
+    }
+
+    fn plan(player_move: Move, score: f32) -> Plan {
+        Plan {
+            player_move,
+            score,
+            move_scores: vec![(player_move, score)],
+            search_depth: 2,
+            nodes_searched: 100,
+        }
+    }
+
+    /// Shouts for `request` from the board it holds, as the router does.
+    fn shout(request: &MoveRequest, plan: &Plan, config: &PlannerConfig) -> Option<String> {
+        let game_state = GameState::from_board(&request.board, &request.you.id).unwrap();
+        super::shout(request, &game_state, plan, config)
+    }
+
+    #[test]
+    fn shouts_follow_the_plan() {
+        let config = PlannerConfig::default();
+        let you = snake("you", 90, &[(5, 5), (5, 4), (5, 3), (5, 2)]);
+        let prey = snake("Prey", 90, &[(8, 5), (9, 5)]);
+        let calm = request(you.clone(), vec![prey.clone()]);
+        assert_eq!(
+            shout(&calm, &plan(Move::Left, 1.0), &config).as_deref(),
+            Some("looking 2 turns ahead")
+        );
+        assert_eq!(
+            shout(&calm, &plan(Move::Left, config.weights.lose), &config).as_deref(),
+            Some("trapped: maximizing survival")
+        );
+
+        let aggressive = PlannerConfig {
+            aggressive: true,
+            ..config
+        };
+        assert_eq!(
+            shout(&calm, &plan(Move::Right, 1.0), &aggressive).as_deref(),
+            Some("hunting Prey")
+        );
+
+        let hungry = request(snake("you", 20, &[(5, 5), (5, 4), (5, 3)]), vec![prey]);
+        assert_eq!(
+            shout(&hungry, &plan(Move::Left, 1.0), &config).as_deref(),
+            Some("low health, seeking food")
+        );
+        assert_eq!(
+            shout(&hungry, &plan(Move::Right, 1.0), &config).as_deref(),
+            Some("looking 2 turns ahead")
+        );
+    }
+
+    #[test]
+    fn shouts_fit_the_api_limit() {
+        let name = "é".repeat(300);
+        let you = snake("you", 90, &[(5, 5), (5, 4), (5, 3), (5, 2)]);
+        let request = request(you, vec![snake(&name, 90, &[(8, 5), (9, 5)])]);
+        let config = PlannerConfig {
+            aggressive: true,
+            ..PlannerConfig::default()
+        };
+        let shout = shout(&request, &plan(Move::Right, 1.0), &config).unwrap();
+        assert_eq!(shout.chars().count(), MAX_SHOUT_LENGTH);
+        assert!(shout.starts_with("hunting éé"));
+    }
+}
@@ -0,0 +1,248 @@
+use rayon::prelude::*;
+
+use crate::{
+    engine::{self, GameOutcome, MatchSettings},
+    error::Result,
+    planner::PlannerConfig,
+};
+
+/// The z-score of a two-sided 95% confidence interval.
+static Z_95: f64 = 1.96;
+/// The rating of an average entrant.
+static BASE_ELO: f64 = 1500.0;
+/// Half a win and half a loss credited to each pair of entrants before any
+/// games, so that an entrant that never wins keeps a finite rating.
+static PRIOR_GAMES: f64 = 0.5;
+static ELO_ITERATIONS: usize = 1000;
+
+/// How one entrant fared over a tournament.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub wins: u32,
+    pub draws: u32,
+    pub games: u32,
+    /// The bounds of the 95% Wilson score interval for the win rate.
+    pub win_rate_interval: (f64, f64),
+    pub elo: f64,
+}
+
+impl Standing {
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            f64::from(self.wins) / f64::from(self.games)
+        }
+    }
+}
+
+/// Plays `games` games in parallel, each between all of `planners`, with
+/// seeds counting up from `seed`. The snakes take turns at each seat so that
+/// no entrant keeps the same starting position. The outcomes are in game
+/// order and give the entrants in the order of `planners`.
+pub fn play(
+    planners: &[PlannerConfig],
+    games: u32,
+    seed: u64,
+    settings: &MatchSettings,
+) -> Result<Vec<GameOutcome>> {
+    (0..games)
+        .into_par_iter()
+        .map(|game| {
+            let seats = seating(planners.len(), game);
+            let seated: Vec<_> = seats.iter().map(|&i| planners[i]).collect();
+            let outcome =
+                engine::simulate_game(&seated, settings, seed.wrapping_add(u64::from(game)))?;
+            let mut eliminations = vec![None; planners.len()];
+            for (seat, &i) in seats.iter().enumerate() {
+                eliminations[i] = outcome.eliminations[seat];
+            }
+            Ok(GameOutcome {
+                winner: outcome.winner.map(|seat| seats[seat]),
+                turns: outcome.turns,
+                eliminations,
+            })
+        })
+        .collect()
+}
+
+/// Which entrant sits at each seat in the given game.
+fn seating(entrants: usize, game: u32) -> Vec<usize> {
+    let shift = game as usize % entrants.max(1);
+    (0..entrants)
+        .map(|seat| (seat + shift) % entrants)
+        .collect()
+}
+
+/// Tallies the outcomes of games between `entrants` snakes.
+///
+/// Ratings treat each game as a set of head-to-head results: a snake beats
+/// every snake eliminated before it, and draws with those that went out on
+/// the same turn or were still going when the game was called.
+pub fn standings(entrants: usize, outcomes: &[GameOutcome]) -> Vec<Standing> {
+    let mut scores = vec![vec![0.0; entrants]; entrants];
+    for outcome in outcomes {
+        let survival: Vec<_> = outcome
+            .eliminations
+            .iter()
+            .map(|e| e.map_or(u32::MAX, |e| e.turn))
+            .collect();
+        for i in 0..entrants {
+            for j in 0..entrants {
+                scores[i][j] += match survival[i].cmp(&survival[j]) {
+                    _ if i == j => 0.0,
+                    std::cmp::Ordering::Greater => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Less => 0.0,
+                };
+            }
+        }
+    }
+    let elo = elo_ratings(&scores);
+
+    (0..entrants)
+        .map(|i| {
+            let wins = outcomes.iter().filter(|o| o.winner == Some(i)).count() as u32;
+            let draws = outcomes.iter().filter(|o| o.winner.is_none()).count() as u32;
+            let games = outcomes.len() as u32;
+            Standing {
+                wins,
+                draws,
+                games,
+                win_rate_interval: wilson_interval(wins, games, Z_95),
+                elo: elo[i],
+            }
+        })
+        .collect()
+}
+
+/// The Wilson score interval for a proportion of `successes` in `trials`,
+/// which unlike the normal approximation stays within 0 to 1 and behaves for
+/// small samples and rates near either end.
+pub fn wilson_interval(successes: u32, trials: u32, z: f64) -> (f64, f64) {
+    if trials == 0 {
+        return (0.0, 1.0);
+    }
+    let n = f64::from(trials);
+    let p = f64::from(successes) / n;
+    let z2 = z * z;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((centre - margin).max(0.0), (centre + margin).min(1.0))
+}
+
+/// Fits a Bradley-Terry model to `scores[i][j]`, the points `i` took from
+/// games against `j`, and returns it as Elo ratings averaging `BASE_ELO`.
+fn elo_ratings(scores: &[Vec<f64>]) -> Vec<f64> {
+    let n = scores.len();
+    let scores: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| {
+                    if i == j {
+                        0.0
+                    } else {
+                        scores[i][j] + PRIOR_GAMES
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let mut strengths = vec![1.0; n];
+    for _ in 0..ELO_ITERATIONS {
+        let updated: Vec<f64> = (0..n)
+            .map(|i| {
+                let points: f64 = scores[i].iter().sum();
+                let expected: f64 = (0..n)
+                    .filter(|&j| j != i)
+                    .map(|j| (scores[i][j] + scores[j][i]) / (strengths[i] + strengths[j]))
+                    .sum();
+                if expected > 0.0 {
+                    points / expected
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        let log_mean = updated.iter().map(|s| s.ln()).sum::<f64>() / n as f64;
+        strengths = updated.iter().map(|s| s / log_mean.exp()).collect();
+    }
+    strengths
+        .iter()
+        .map(|s| BASE_ELO + 400.0 * s.log10())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Elimination, EliminationCause};
+
+    #[test]
+    fn wilson_intervals_contain_the_rate() {
+        let (low, high) = wilson_interval(60, 100, Z_95);
+        assert!((low - 0.502).abs() < 0.001, "{}", low);
+        assert!((high - 0.691).abs() < 0.001, "{}", high);
+        assert_eq!(wilson_interval(0, 10, Z_95).0, 0.0);
+        assert_eq!(wilson_interval(10, 10, Z_95).1, 1.0);
+        assert_eq!(wilson_interval(0, 0, Z_95), (0.0, 1.0));
+    }
+
+    #[test]
+    fn standings_rank_by_survival() {
+        let out = |turn| {
+            Some(Elimination {
+                cause: EliminationCause::OutOfHealth,
+                turn,
+            })
+        };
+        let outcomes = [
+            GameOutcome {
+                winner: Some(0),
+                turns: 30,
+                eliminations: vec![None, out(30), out(10)],
+            },
+            GameOutcome {
+                winner: Some(0),
+                turns: 40,
+                eliminations: vec![None, out(20), out(40)],
+            },
+            GameOutcome {
+                winner: None,
+                turns: 50,
+                eliminations: vec![out(50), out(50), out(20)],
+            },
+        ];
+        let standings = standings(3, &outcomes);
+        assert_eq!(standings[0].wins, 2);
+        assert_eq!(standings[1].wins, 0);
+        assert_eq!(standings[0].draws, 1);
+        assert!(standings[0].elo > standings[1].elo);
+        assert!(standings[1].elo > standings[2].elo);
+        let mean = standings.iter().map(|s| s.elo).sum::<f64>() / 3.0;
+        assert!((mean - BASE_ELO).abs() < 1e-6);
+    }
+
+    #[test]
+    fn tournaments_follow_the_seed() {
+        let planners = [
+            PlannerConfig {
+                search_depth: 1,
+                ..PlannerConfig::default()
+            },
+            PlannerConfig {
+                search_depth: 1,
+                aggressive: true,
+                ..PlannerConfig::default()
+            },
+        ];
+        let settings = MatchSettings {
+            width: 7,
+            height: 7,
+            ..MatchSettings::default()
+        };
+        let outcomes = play(&planners, 4, 2, &settings).unwrap();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(play(&planners, 4, 2, &settings).unwrap(), outcomes);
+    }
+}
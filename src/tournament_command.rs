@@ -0,0 +1,91 @@
+use {clap::Args, std::time::Instant};
+
+use blaze_haskell::{
+    config::Config,
+    engine::MatchSettings,
+    error::{Error, Result},
+    tournament,
+};
+
+use crate::play_command::local_planner;
+
+/// Plays many seeded games between planner settings and reports how each
+/// did.
+#[derive(Args)]
+pub struct TournamentArgs {
+    /// An entrant: "local" for the planner as configured or "local:PREFIX"
+    /// for one of the configured personalities. Give once per entrant; every
+    /// game is played between all of them.
+    #[arg(long = "snake", required = true)]
+    snakes: Vec<String>,
+    /// How many games to play
+    #[arg(long, default_value_t = 100)]
+    games: u32,
+    /// Seed for the first game; each further game adds one
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long, default_value_t = 11)]
+    width: u16,
+    #[arg(long, default_value_t = 11)]
+    height: u16,
+    /// Call games still going after this many turns a draw
+    #[arg(long)]
+    max_turns: Option<u32>,
+}
+
+pub fn run(args: &TournamentArgs, config: &Config) -> Result<()> {
+    let planners = args
+        .snakes
+        .iter()
+        .map(|spec| {
+            local_planner(spec, config)?.ok_or_else(|| {
+                Error::config(format!(
+                    "Unknown snake \"{}\"; tournaments are between \"local\" and \"local:PREFIX\" snakes.",
+                    spec
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let settings = MatchSettings {
+        width: args.width,
+        height: args.height,
+        max_turns: args.max_turns,
+        ..MatchSettings::default()
+    };
+
+    let start = Instant::now();
+    let outcomes = tournament::play(&planners, args.games, args.seed, &settings)?;
+    let standings = tournament::standings(planners.len(), &outcomes);
+    let draws = outcomes.iter().filter(|o| o.winner.is_none()).count();
+    let total_turns: u32 = outcomes.iter().map(|o| o.turns).sum();
+
+    println!(
+        "Played {} games in {:.1}s, {} drawn",
+        outcomes.len(),
+        start.elapsed().as_secs_f64(),
+        draws
+    );
+    if !outcomes.is_empty() {
+        println!(
+            "Average game length: {:.1} turns",
+            f64::from(total_turns) / outcomes.len() as f64
+        );
+    }
+    println!(
+        "  {:<30} {:>6} {:>9} {:>17} {:>7}",
+        "snake", "wins", "win rate", "95% interval", "Elo"
+    );
+    for (spec, standing) in args.snakes.iter().zip(&standings) {
+        let (low, high) = standing.win_rate_interval;
+        println!(
+            "  {:<30} {:>6} {:>8.1}% {:>7.1}% - {:>5.1}% {:>7.0}",
+            spec,
+            standing.wins,
+            100.0 * standing.win_rate(),
+            100.0 * low,
+            100.0 * high,
+            standing.elo
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use {
+    clap::Args,
+    serde::Serialize,
+    std::{path::PathBuf, time::Instant},
+};
+
+use blaze_haskell::{
+    engine::MatchSettings,
+    error::{Error, Result},
+    planner::PlannerConfig,
+    tuning::{self, TuningSettings},
+};
+
+/// Searches for better planner weights by self-play against the configured
+/// planner, and writes the best found as a configuration file.
+#[derive(Args)]
+pub struct TuneArgs {
+    /// Where to write the tuned configuration
+    #[arg(long, default_value = "tuned.toml")]
+    output: PathBuf,
+    /// Candidate weightings in each generation
+    #[arg(long, default_value_t = 12)]
+    population: usize,
+    #[arg(long, default_value_t = 10)]
+    generations: u32,
+    /// Games each candidate plays against the configured planner per
+    /// generation
+    #[arg(long, default_value_t = 20)]
+    games: u32,
+    /// Standard deviation of the log of each mutation's scale factor
+    #[arg(long, default_value_t = 0.2)]
+    mutation: f32,
+    /// Seed for the whole run; the same seed gives the same result
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long, default_value_t = 11)]
+    width: u16,
+    #[arg(long, default_value_t = 11)]
+    height: u16,
+    /// Call games still going after this many turns a draw
+    #[arg(long)]
+    max_turns: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct TunedConfig<'a> {
+    planner: &'a PlannerConfig,
+}
+
+pub fn run(args: &TuneArgs, baseline: &PlannerConfig) -> Result<()> {
+    let settings = TuningSettings {
+        population: args.population,
+        generations: args.generations,
+        games: args.games,
+        mutation: args.mutation,
+        seed: args.seed,
+        matches: MatchSettings {
+            width: args.width,
+            height: args.height,
+            max_turns: args.max_turns,
+            ..MatchSettings::default()
+        },
+    };
+
+    let start = Instant::now();
+    let best = tuning::tune(baseline, &settings, |generation| {
+        let mean = generation.candidates.iter().map(|c| c.fitness).sum::<f64>()
+            / generation.candidates.len() as f64;
+        println!(
+            "Generation {} after {:.1}s: best {:.3}, mean {:.3}",
+            generation.number + 1,
+            start.elapsed().as_secs_f64(),
+            generation.candidates[0].fitness,
+            mean
+        );
+    })?;
+
+    println!(
+        "Best weights, scoring {:.3} against the baseline:",
+        best.fitness
+    );
+    for (name, value) in best.weights.named() {
+        println!("  {:<22} {}", name, value);
+    }
+    let planner = PlannerConfig {
+        weights: best.weights,
+        ..*baseline
+    };
+    let contents = format!(
+        "# Tuned with --seed {} over {} generations of {} games; scored {:.3}\n# against the baseline in the last.\n{}",
+        args.seed,
+        args.generations,
+        args.games,
+        best.fitness,
+        toml::to_string(&TunedConfig { planner: &planner })
+            .map_err(|e| Error::parse("Could not write the tuned configuration", e))?
+    );
+    std::fs::write(&args.output, contents)
+        .map_err(|e| Error::io(format!("Could not write {}", args.output.display()), e))?;
+    println!("Wrote {}", args.output.display());
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use {oorandom::Rand32, rayon::prelude::*};
+
+use crate::{
+    engine::MatchSettings,
+    error::Result,
+    planner::{PlannerConfig, Weights},
+    tournament,
+};
+
+/// How many of the fittest candidates carry over to the next generation
+/// unchanged.
+static ELITE_COUNT: usize = 2;
+/// How many candidates compete to be each parent.
+static SELECTION_SIZE: usize = 3;
+
+/// How a tuning run is set up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuningSettings {
+    /// How many candidate weightings make up each generation.
+    pub population: usize,
+    pub generations: u32,
+    /// How many games each candidate plays against the baseline in each
+    /// generation.
+    pub games: u32,
+    /// The standard deviation of the log of the factor each weight is
+    /// multiplied by when mutated.
+    pub mutation: f32,
+    /// Everything random in the run, including the games, follows from this.
+    pub seed: u64,
+    pub matches: MatchSettings,
+}
+
+impl Default for TuningSettings {
+    fn default() -> Self {
+        Self {
+            population: 12,
+            generations: 10,
+            games: 20,
+            mutation: 0.2,
+            seed: 0,
+            matches: MatchSettings::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    pub weights: Weights,
+    /// The share of its games the candidate won against the baseline, with
+    /// draws counting half.
+    pub fitness: f64,
+}
+
+/// The candidates of one generation, fittest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generation {
+    pub number: u32,
+    pub candidates: Vec<Candidate>,
+}
+
+/// Searches for better weights for `baseline` with a genetic algorithm. Each
+/// candidate's fitness is how it does in self-play games against `baseline`
+/// itself, all candidates of a generation playing the same seeds. The fittest
+/// carry over and the rest are bred, by uniform crossover and mutation, from
+/// parents that each won a small tournament of fitness. Calls
+/// `on_generation` once each generation has played, and returns the fittest
+/// candidate of the last one.
+pub fn tune(
+    baseline: &PlannerConfig,
+    settings: &TuningSettings,
+    mut on_generation: impl FnMut(&Generation),
+) -> Result<Candidate> {
+    let mut rng = Rand32::new(settings.seed);
+    let mut population = vec![baseline.weights];
+    while population.len() < settings.population.max(1) {
+        population.push(mutate(&baseline.weights, settings.mutation, &mut rng));
+    }
+
+    let mut best = None;
+    for number in 0..settings.generations {
+        let seed = settings
+            .seed
+            .wrapping_add(u64::from(number) * u64::from(settings.games));
+        let fitness = population
+            .par_iter()
+            .map(|&weights| {
+                let candidate = PlannerConfig {
+                    weights,
+                    ..*baseline
+                };
+                evaluate(&candidate, baseline, settings, seed)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut candidates: Vec<_> = population
+            .iter()
+            .zip(fitness)
+            .map(|(&weights, fitness)| Candidate { weights, fitness })
+            .collect();
+        candidates.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        let generation = Generation { number, candidates };
+        on_generation(&generation);
+        best = generation.candidates.first().copied();
+
+        population = generation
+            .candidates
+            .iter()
+            .take(ELITE_COUNT)
+            .map(|c| c.weights)
+            .collect();
+        while population.len() < generation.candidates.len() {
+            let a = select(&generation.candidates, &mut rng);
+            let b = select(&generation.candidates, &mut rng);
+            let child = crossover(&a, &b, &mut rng);
+            population.push(mutate(&child, settings.mutation, &mut rng));
+        }
+    }
+    Ok(best.unwrap_or(Candidate {
+        weights: baseline.weights,
+        fitness: 0.0,
+    }))
+}
+
+fn evaluate(
+    candidate: &PlannerConfig,
+    baseline: &PlannerConfig,
+    settings: &TuningSettings,
+    seed: u64,
+) -> Result<f64> {
+    let outcomes = tournament::play(
+        &[*candidate, *baseline],
+        settings.games,
+        seed,
+        &settings.matches,
+    )?;
+    let points: f64 = outcomes
+        .iter()
+        .map(|outcome| match outcome.winner {
+            Some(0) => 1.0,
+            None => 0.5,
+            Some(_) => 0.0,
+        })
+        .sum();
+    Ok(points / outcomes.len().max(1) as f64)
+}
+
+/// The fittest of a few candidates picked at random.
+fn select(candidates: &[Candidate], rng: &mut Rand32) -> Weights {
+    (0..SELECTION_SIZE)
+        .map(|_| rng.rand_range(0..candidates.len() as u32) as usize)
+        .min()
+        .map_or(candidates[0].weights, |i| candidates[i].weights)
+}
+
+/// Takes each weight from either parent at random.
+fn crossover(a: &Weights, b: &Weights, rng: &mut Rand32) -> Weights {
+    let mut values = [0.0; 7];
+    for (value, ((_, a), (_, b))) in values.iter_mut().zip(a.named().into_iter().zip(b.named())) {
+        *value = if rng.rand_range(0..2) == 0 { a } else { b };
+    }
+    Weights::from_values(values)
+}
+
+/// Scales each weight by a random factor, so that weights keep their sign and
+/// small weights make small steps.
+fn mutate(weights: &Weights, mutation: f32, rng: &mut Rand32) -> Weights {
+    Weights::from_values(
+        weights
+            .named()
+            .map(|(_, value)| value * (mutation * normal(rng)).exp()),
+    )
+}
+
+/// A standard normal sample, by the Box-Muller transform.
+fn normal(rng: &mut Rand32) -> f32 {
+    let u = 1.0 - rng.rand_float();
+    let v = rng.rand_float();
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuning_follows_the_seed() {
+        let baseline = PlannerConfig {
+            search_depth: 1,
+            ..PlannerConfig::default()
+        };
+        let settings = TuningSettings {
+            population: 4,
+            generations: 2,
+            games: 2,
+            matches: MatchSettings {
+                width: 7,
+                height: 7,
+                max_turns: Some(50),
+                ..MatchSettings::default()
+            },
+            ..TuningSettings::default()
+        };
+        let mut generations = vec![];
+        let best = tune(&baseline, &settings, |g| generations.push(g.clone())).unwrap();
+        assert_eq!(generations.len(), 2);
+        assert!(generations.iter().all(|g| g.candidates.len() == 4));
+        assert!(
+            generations[0]
+                .candidates
+                .iter()
+                .any(|c| c.weights == baseline.weights)
+        );
+        assert_eq!(best, generations[1].candidates[0]);
+        assert_eq!(tune(&baseline, &settings, |_| {}).unwrap(), best);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{
+    api_types::{Battlesnake, Board, Coordinates},
+    game_state::{MAX_BOARD_SIZE, MAX_SNAKES},
+};
+
+/// What is wrong with a request from the game server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    BoardSize {
+        width: i32,
+        height: i32,
+    },
+    TooManySnakes(usize),
+    /// `what` names the food, hazard or snake the cell belongs to.
+    OffBoard {
+        what: String,
+        x: i32,
+        y: i32,
+    },
+    EmptyBody {
+        snake: String,
+    },
+    HeadMismatch {
+        snake: String,
+    },
+    LengthMismatch {
+        snake: String,
+        length: u16,
+        body: usize,
+    },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BoardSize { width, height } => write!(
+                f,
+                "Board size {}x{} is not between 1x1 and {}x{}.",
+                width, height, MAX_BOARD_SIZE, MAX_BOARD_SIZE
+            ),
+            Self::TooManySnakes(count) => write!(
+                f,
+                "Board has {} snakes, more than the {} supported.",
+                count, MAX_SNAKES
+            ),
+            Self::OffBoard { what, x, y } => {
+                write!(f, "Cell ({}, {}) of {} is off the board.", x, y, what)
+            }
+            Self::EmptyBody { snake } => write!(f, "Snake \"{}\" has an empty body.", snake),
+            Self::HeadMismatch { snake } => write!(
+                f,
+                "Head of snake \"{}\" is not the first cell of its body.",
+                snake
+            ),
+            Self::LengthMismatch {
+                snake,
+                length,
+                body,
+            } => write!(
+                f,
+                "Snake \"{}\" has length {} but {} body cells.",
+                snake, length, body
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+pub type ValidationResult = std::result::Result<(), ValidationError>;
+
+impl Board {
+    /// Checks that the board is one the planner can take on: of a supported
+    /// size and number of snakes, with every snake well formed and every cell
+    /// on the board.
+    pub fn validate(&self) -> ValidationResult {
+        if !(1..=MAX_BOARD_SIZE).contains(&self.width)
+            || !(1..=MAX_BOARD_SIZE).contains(&self.height)
+        {
+            return Err(ValidationError::BoardSize {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        if self.snakes.len() > MAX_SNAKES {
+            return Err(ValidationError::TooManySnakes(self.snakes.len()));
+        }
+        for snake in &self.snakes {
+            snake.validate(self)?;
+        }
+        for (what, cells) in [("food", &self.food), ("a hazard", &self.hazards)] {
+            for cell in cells {
+                self.check_on_board(cell, what)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_on_board(&self, cell: &Coordinates, what: &str) -> ValidationResult {
+        if (0..self.width).contains(&cell.x) && (0..self.height).contains(&cell.y) {
+            Ok(())
+        } else {
+            Err(ValidationError::OffBoard {
+                what: what.into(),
+                x: cell.x,
+                y: cell.y,
+            })
+        }
+    }
+}
+
+impl Battlesnake {
+    /// Checks that the snake is consistent and lies on `board`.
+    pub fn validate(&self, board: &Board) -> ValidationResult {
+        let Some(first) = self.body.first() else {
+            return Err(ValidationError::EmptyBody {
+                snake: self.id.clone(),
+            });
+        };
+        if (first.x, first.y) != (self.head.x, self.head.y) {
+            return Err(ValidationError::HeadMismatch {
+                snake: self.id.clone(),
+            });
+        }
+        if usize::from(self.length) != self.body.len() {
+            return Err(ValidationError::LengthMismatch {
+                snake: self.id.clone(),
+                length: self.length,
+                body: self.body.len(),
+            });
+        }
+        let what = format!("snake \"{}\"", self.id);
+        for cell in &self.body {
+            board.check_on_board(cell, &what)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(snakes: Vec<Battlesnake>) -> Board {
+        Board {
+            height: 11,
+            width: 11,
+            food: vec![],
+            hazards: vec![],
+            snakes,
+        }
+    }
+
+    fn snake(id: &str, body: &[(i32, i32)]) -> Battlesnake {
+        let body: Vec<_> = body.iter().map(|&(x, y)| Coordinates { x, y }).collect();
+        Battlesnake {
+            id: id.into(),
+            name: id.into(),
+            health: 100,
+            head: body[0].clone(),
+            length: body.len() as u16,
+            body,
+            latency: "0".into(),
+            shout: "".into(),
+            squad: "".into(),
+            customizations: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn boards_are_validated() {
+        let you = snake("you", &[(1, 1), (1, 2), (1, 3)]);
+        assert_eq!(board(vec![you.clone()]).validate(), Ok(()));
+
+        let mut wrong_head = you.clone();
+        wrong_head.head = Coordinates { x: 1, y: 2 };
+        assert_eq!(
+            board(vec![wrong_head]).validate(),
+            Err(ValidationError::HeadMismatch {
+                snake: "you".into()
+            })
+        );
+
+        let mut wrong_length = you.clone();
+        wrong_length.length = 4;
+        assert_eq!(
+            board(vec![wrong_length]).validate(),
+            Err(ValidationError::LengthMismatch {
+                snake: "you".into(),
+                length: 4,
+                body: 3
+            })
+        );
+
+        let off_board = snake("them", &[(10, 10), (10, 11)]);
+        assert_eq!(
+            board(vec![you.clone(), off_board]).validate(),
+            Err(ValidationError::OffBoard {
+                what: "snake \"them\"".into(),
+                x: 10,
+                y: 11
+            })
+        );
+
+        let mut food = board(vec![you.clone()]);
+        food.food.push(Coordinates { x: -1, y: 0 });
+        assert!(matches!(
+            food.validate(),
+            Err(ValidationError::OffBoard { .. })
+        ));
+
+        let mut huge = board(vec![you]);
+        huge.width = 1000;
+        assert!(matches!(
+            huge.validate(),
+            Err(ValidationError::BoardSize { .. })
+        ));
+    }
+}
@@ -0,0 +1,42 @@
+//! Runs the planner on every puzzle in `tests/puzzles` and checks its move.
+//! See `blaze_haskell::puzzle::Puzzle` for the format, and `plan --puzzle` to
+//! turn a recorded turn into a new case.
+
+use std::path::Path;
+
+use blaze_haskell::{planner::SearchTree, puzzle::Puzzle};
+
+#[test]
+fn planner_solves_puzzles() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/puzzles");
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "toml"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no puzzles in {}", dir.display());
+
+    let mut failures = vec![];
+    for path in &paths {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let puzzle = Puzzle::load(path).unwrap_or_else(|e| panic!("{}", e));
+        let game_state = puzzle
+            .game_state(&dir)
+            .unwrap_or_else(|e| panic!("{}: {}", name, e));
+        let plan = SearchTree::new(game_state.clone()).find_plan(&puzzle.planner, &[], None);
+        if !puzzle.accepts(plan.player_move) {
+            failures.push(format!(
+                "{}: {}\n{}chose {:?} from {:?}",
+                name, puzzle.description, game_state, plan.player_move, plan.move_scores
+            ));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} of {} puzzles failed:\n\n{}",
+        failures.len(),
+        paths.len(),
+        failures.join("\n\n")
+    );
+}